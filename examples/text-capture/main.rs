@@ -28,7 +28,7 @@ fn main() -> crossterm::Result<()> {
 
 // Capsulate to contain raw mode
 fn event_loop() -> crossterm::Result<()> {
-    let mut buffer = Rc::new(RefCell::<String>::new(String::new()));
+    let buffer = Rc::new(RefCell::<String>::new(String::new()));
 
     let mut cap_ext = TextCapture::capture(buffer, 0, InputSource::Stdio(None));
     let mut cap_int = TextCapture::owned(InputSource::Stdio(None));
@@ -58,8 +58,8 @@ fn event_loop() -> crossterm::Result<()> {
         .queue(cursor::MoveTo(0, 0))?
         .flush()?;
 
-    cap_int.draw_at(0, 0, io::stdout())?;
-    cap_ext.draw_at(0, 1, io::stdout())?;
+    cap_int.draw_at(0, 0, &mut io::stdout())?;
+    cap_ext.draw_at(0, 1, &mut io::stdout())?;
     format!(
         "Press {:?} to edit, {:?} / {:?} to exit",
         crossterm::event::KeyCode::Enter,
@@ -70,7 +70,7 @@ fn event_loop() -> crossterm::Result<()> {
         }
     )
     .as_str()
-    .draw_at(0, 2, io::stdout())?;
+    .draw_at(0, 2, &mut io::stdout())?;
 
     while run {
         let input = crossterm::event::read()?;
@@ -82,7 +82,7 @@ fn event_loop() -> crossterm::Result<()> {
                     _ => unreachable!(),
                 };
 
-                let (redraw, cur_delta) = buf.input(&key);
+                let (redraw, _cur_delta) = buf.input(&key);
                 /*if cur_delta > 0 {
                     io::stdout().queue(cursor::MoveRight)?;
                 } else if cur_delta < 0 {
@@ -90,7 +90,7 @@ fn event_loop() -> crossterm::Result<()> {
                 }*/
                 if redraw {
                     io::stdout().flush()?;
-                    buf.draw_at(0, edit_idx, io::stdout())?;
+                    buf.draw_at(0, edit_idx, &mut io::stdout())?;
                 }
             }
             use crossterm::event::KeyCode;
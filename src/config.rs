@@ -0,0 +1,199 @@
+//! User configuration, read from `config.toml` in the user's config directory,
+//! e.g. `~/.config/daily-planner/config.toml`. Command line flags take
+//! precedence over the config.
+//!
+//! ```toml
+//! template = "/home/me/plans/template.md"
+//! data-dir = "/home/me/plans"
+//! span-len = "3:15"
+//! max-gap = "1:30"
+//! time-zone = "Europe/Helsinki"
+//!
+//! [wake-up]
+//! default = "09:00"
+//! sat = "10:30"
+//!
+//! [location]
+//! lat = 61.44
+//! lng = 23.87
+//!
+//! [twilight]
+//! source = "offline"
+//!
+//! [theme]
+//! night = "#6464ff"
+//!
+//! [keys.cursor]
+//! x = "cut-current-line"
+//! ```
+use chrono::{NaiveDate, Weekday};
+use fs_err as fs;
+use serde::Deserialize;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+use crate::{
+    editor::{keymap::KeyConfig, Theme},
+    time::{Duration, Time},
+    twilight::{Location, TimeZone},
+};
+
+const CONFIG_FILE: &str = "config.toml";
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    pub wake_up: WakeUp,
+    /// The template used when no schedule is opened
+    pub template: Option<PathBuf>,
+    /// Where the schedule of each day is saved, see `Config::schedule_file`
+    pub data_dir: Option<PathBuf>,
+    /// Default length of spans
+    pub span_len: Option<Duration>,
+    /// Gaps between items longer than this are pointed out
+    pub max_gap: Option<Duration>,
+    pub location: Option<Location>,
+    pub time_zone: Option<TimeZone>,
+    pub twilight: TwilightConfig,
+    pub theme: Theme,
+    /// Key bindings merged over the defaults, see `editor::keymap`
+    pub keys: KeyConfig,
+}
+
+/// Wake-up times per weekday, falling back to the default.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WakeUp {
+    pub default: Option<Time>,
+    pub mon: Option<Time>,
+    pub tue: Option<Time>,
+    pub wed: Option<Time>,
+    pub thu: Option<Time>,
+    pub fri: Option<Time>,
+    pub sat: Option<Time>,
+    pub sun: Option<Time>,
+}
+
+impl WakeUp {
+    pub fn on(&self, weekday: Weekday) -> Option<Time> {
+        let time = match weekday {
+            Weekday::Mon => self.mon,
+            Weekday::Tue => self.tue,
+            Weekday::Wed => self.wed,
+            Weekday::Thu => self.thu,
+            Weekday::Fri => self.fri,
+            Weekday::Sat => self.sat,
+            Weekday::Sun => self.sun,
+        };
+        time.or(self.default)
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TwilightConfig {
+    pub source: TwilightSource,
+    /// Sunrise for the fixed source
+    pub sunrise: Option<Time>,
+    /// Sunset for the fixed source
+    pub sunset: Option<Time>,
+}
+
+/// Where sunrise and sunset come from
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TwilightSource {
+    /// Calculated offline
+    #[default]
+    Offline,
+    /// Fetched from api.sunrise-sunset.org
+    Online,
+    /// Given in the config or by command line
+    Fixed,
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("config error: {0}")]
+    Toml(#[from] toml::de::Error),
+}
+
+impl Config {
+    /// The default location of the config file, if the platform has one
+    pub fn default_file() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("daily-planner").join(CONFIG_FILE))
+    }
+
+    /// The file the schedule of given day is saved into, e.g.
+    /// `~/.local/share/daily-planner/2021-02-28.md`. Without a configured
+    /// data directory the platform's one is used, or `data` if there is none.
+    pub fn schedule_file(&self, date: NaiveDate) -> PathBuf {
+        let dir = self.data_dir.clone().unwrap_or_else(|| {
+            dirs::data_dir()
+                .map(|dir| dir.join("daily-planner"))
+                .unwrap_or_else(|| PathBuf::from("data"))
+        });
+        dir.join(format!("{}.md", date))
+    }
+
+    /// Reads the config from given file. A missing file is an empty config.
+    pub fn load(file: &Path) -> Result<Config, Error> {
+        match fs::read_to_string(file) {
+            Ok(text) => Ok(toml::from_str(&text)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[test]
+fn config_from_toml() {
+    let config: Config = toml::from_str(
+        r##"
+        span-len = "2:30"
+        data-dir = "/plans"
+        time-zone = "+02:00"
+
+        [wake-up]
+        default = "09:00"
+        sat = "10:30"
+
+        [location]
+        lat = 60.17
+        lng = 24.94
+
+        [twilight]
+        source = "fixed"
+        sunrise = "06:00"
+
+        [theme]
+        night = "#0a0bff"
+        "##,
+    )
+    .unwrap();
+
+    assert_eq!(config.wake_up.on(Weekday::Sat), Some(Time::hm(10, 30)));
+    assert_eq!(config.wake_up.on(Weekday::Mon), Some(Time::hm(9, 0)));
+    assert_eq!(config.span_len, Some(Duration::hm(2, 30)));
+    assert_eq!(
+        config.schedule_file(NaiveDate::from_ymd(2021, 2, 28)),
+        PathBuf::from("/plans/2021-02-28.md")
+    );
+    assert_eq!(config.twilight.source, TwilightSource::Fixed);
+    assert_eq!(
+        config.theme.night,
+        crossterm::style::Color::Rgb {
+            r: 10,
+            g: 11,
+            b: 255
+        }
+    );
+    assert_eq!(config.theme.day, Theme::default().day);
+
+    assert!(toml::from_str::<Config>("wake-up = { tue = \"7:3O\" }").is_err());
+}
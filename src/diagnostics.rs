@@ -0,0 +1,165 @@
+//! Consistency checks of a schedule: items that overlap, times out of order
+//! and long gaps between items. Times are counted from wake-up, so that items
+//! past midnight belong to the end of the day.
+use std::fmt;
+
+use crate::{
+    schedule::Schedule,
+    time::{Duration, Time},
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+    /// Starts before the span on given line has ended
+    Overlap { with: usize },
+    /// Starts before the item on given line, which is above it
+    OutOfOrder { after: usize },
+    /// Starts this long after the span before it has ended
+    Gap(Duration),
+}
+
+/// A problem found on a line of the schedule
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub problem: Problem,
+}
+
+impl Diagnostic {
+    /// The other line of an overlap or of times out of order. Gaps are only
+    /// suspicious, not conflicts.
+    pub fn conflicts_with(&self) -> Option<usize> {
+        match self.problem {
+            Problem::Overlap { with } => Some(with),
+            Problem::OutOfOrder { after } => Some(after),
+            Problem::Gap(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Lines are numbered from 1 onwards
+        match &self.problem {
+            Problem::Overlap { with } => {
+                write!(f, "line {} overlaps line {}", self.line + 1, with + 1)
+            }
+            Problem::OutOfOrder { after } => {
+                write!(f, "line {} starts before line {}", self.line + 1, after + 1)
+            }
+            Problem::Gap(gap) => write!(f, "{} gap before line {}", gap, self.line + 1),
+        }
+    }
+}
+
+/// Checks the timed items of a schedule in order. Gaps longer than max_gap
+/// are reported. Items without a span last until the next timed item, so
+/// only spans can overlap or leave gaps.
+pub fn check(schedule: &Schedule, max_gap: &Duration) -> Vec<Diagnostic> {
+    let since_wake_up = |t: &Time| t.minutes_since(&schedule.wake_up);
+
+    let mut diagnostics = vec![];
+    // The line, start and end of the previous timed item, and the line and
+    // end of the span reaching furthest so far
+    let mut previous: Option<(usize, u16, Option<u16>)> = None;
+    let mut reach: Option<(usize, u16)> = None;
+    for (line, time_box) in schedule.timeboxes.iter().enumerate() {
+        let slot = match &time_box.time {
+            Some(slot) => slot,
+            None => continue,
+        };
+        let start = since_wake_up(slot.start());
+        let end = slot.end().map(since_wake_up);
+
+        let problem = match (previous, reach) {
+            (Some((after, previous_start, _)), _) if start < previous_start => {
+                Some(Problem::OutOfOrder { after })
+            }
+            (_, Some((with, reach_end))) if start < reach_end => Some(Problem::Overlap { with }),
+            (Some((_, _, Some(_))), Some((_, reach_end)))
+                if (start - reach_end) as i64 > max_gap.num_minutes() =>
+            {
+                let gap = start - reach_end;
+                Some(Problem::Gap(Duration::hm(
+                    (gap / 60) as i8,
+                    (gap % 60) as i8,
+                )))
+            }
+            _ => None,
+        };
+        if let Some(problem) = problem {
+            diagnostics.push(Diagnostic { line, problem });
+        }
+
+        // A time without a span ends where the next item starts
+        let reaches = end.unwrap_or(start);
+        if !matches!(reach, Some((_, reach_end)) if reaches < reach_end) {
+            reach = Some((line, reaches));
+        }
+        previous = Some((line, start, end));
+    }
+    diagnostics
+}
+
+/// Counts of each kind of problem, e.g. "2 overlaps, 1 gap"
+pub fn summary(diagnostics: &[Diagnostic]) -> String {
+    let (mut overlaps, mut out_of_order, mut gaps) = (0, 0, 0);
+    for diagnostic in diagnostics {
+        match diagnostic.problem {
+            Problem::Overlap { .. } => overlaps += 1,
+            Problem::OutOfOrder { .. } => out_of_order += 1,
+            Problem::Gap(_) => gaps += 1,
+        }
+    }
+
+    [
+        (overlaps, "overlap", "overlaps"),
+        (out_of_order, "out of order", "out of order"),
+        (gaps, "gap", "gaps"),
+    ]
+    .iter()
+    .filter(|(n, _, _)| *n > 0)
+    .map(|(n, one, many)| format!("{} {}", n, if *n == 1 { one } else { many }))
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+#[test]
+fn schedule_problems() {
+    use std::str::FromStr;
+
+    let schedule = Schedule::from_str(
+        "- [ ] 09:00--10:00 Work\n\
+         - [ ] 09:30 Call\n\
+         - [ ] 09:15 Coffee\n\
+         - [ ] Untimed\n\
+         - [ ] 10:00--11:00 Write\n\
+         - [ ] 14:00 Lunch\n\
+         - [ ] 15:00 Walk\n",
+    )
+    .unwrap();
+    let diagnostics = check(&schedule, &Duration::hm(1, 0));
+
+    assert_eq!(
+        diagnostics,
+        vec![
+            Diagnostic {
+                line: 1,
+                problem: Problem::Overlap { with: 0 }
+            },
+            Diagnostic {
+                line: 2,
+                problem: Problem::OutOfOrder { after: 1 }
+            },
+            Diagnostic {
+                line: 5,
+                problem: Problem::Gap(Duration::hm(3, 0))
+            },
+        ]
+    );
+    assert_eq!(diagnostics[0].to_string(), "line 2 overlaps line 1");
+    assert_eq!(summary(&diagnostics), "1 overlap, 1 out of order, 1 gap");
+
+    // Within the threshold
+    assert_eq!(check(&schedule, &Duration::hm(3, 0)).len(), 2);
+}
@@ -2,7 +2,7 @@ use std::{fmt, str::FromStr};
 use thiserror::Error;
 
 /// Represents an activity with a kind and a summary. Kind is unkown my default.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Activity {
     pub summary: String,
     pub kind: ActivityKind,
@@ -30,6 +30,17 @@ pub enum ActivityKind {
     Sprint,
 }
 
+/// e.g. meal
+impl fmt::Display for ActivityKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ActivityKind::Unknown => "unknown",
+            ActivityKind::Meal => "meal",
+            ActivityKind::Sprint => "sprint",
+        })
+    }
+}
+
 // Errors
 
 #[derive(Error, Debug)]
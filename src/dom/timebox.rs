@@ -7,7 +7,7 @@ use super::TimeSlotKind;
 ///
 /// A time box can be done or not done. Adjust policy determines how the time
 /// box moves in relation to having its or its surroundings time change.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct TimeBox {
     pub time: Option<TimeSlotKind>,
     pub activity: Activity,
@@ -21,14 +21,31 @@ impl TimeSlotKind {
         // If no times found, return wake up
         TimeSlotKind::Time(schedule.wake_up)
     }
+
+    /// The time, or the start of the span
+    pub fn start(&self) -> &Time {
+        match self {
+            TimeSlotKind::Time(t) => t,
+            TimeSlotKind::Span(start, _) => start,
+        }
+    }
+
+    /// The end of the span, None for a time
+    pub fn end(&self) -> Option<&Time> {
+        match self {
+            TimeSlotKind::Time(_) => None,
+            TimeSlotKind::Span(_, end) => Some(end),
+        }
+    }
+
     pub fn adjust_absolute(&mut self, adjust_duration: &Duration, adjust_start: bool) {
         match self {
-            TimeSlotKind::Time(t) => t.adjust(&adjust_duration),
+            TimeSlotKind::Time(t) => t.adjust(adjust_duration),
             TimeSlotKind::Span(start, end) => {
                 if adjust_start {
-                    start.adjust(&adjust_duration);
+                    start.adjust(adjust_duration);
                 };
-                end.adjust(&adjust_duration);
+                end.adjust(adjust_duration);
             }
         };
     }
@@ -48,14 +65,6 @@ impl fmt::Display for TimeSlotKind {
 
 impl PartialOrd for TimeSlotKind {
     fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
-        let a = match self {
-            TimeSlotKind::Time(t) => t,
-            TimeSlotKind::Span(s, _) => s,
-        };
-        let b = match other {
-            TimeSlotKind::Time(t) => t,
-            TimeSlotKind::Span(s, _) => s,
-        };
-        a.partial_cmp(b)
+        self.start().partial_cmp(other.start())
     }
 }
@@ -1,12 +1,9 @@
 use std::{io::Stdout, panic};
 
-use crate::editor;
+use crate::editor::{self, events::TerminalEvents};
 use crate::schedule::Schedule;
 use backtrace::Backtrace;
-use crossterm::{
-    terminal::{disable_raw_mode, enable_raw_mode},
-    Result,
-};
+use crossterm::{terminal::disable_raw_mode, Result};
 
 pub trait EditorLike<Ed>
 where
@@ -20,7 +17,7 @@ impl EditorLike<editor::State> for editor::State {
     /// Creates the editor, attaches to stdout and sets raw mode
     fn try_from_schedule(schedule: Schedule, stdout: Stdout) -> Result<editor::State> {
         // Enable raw mode and disable it on panic
-        enable_raw_mode()?;
+        let events = TerminalEvents::raw()?;
         panic::set_hook(Box::new(|panic_info| {
             let disable_result = disable_raw_mode();
             if disable_result.is_err() {
@@ -32,7 +29,8 @@ impl EditorLike<editor::State> for editor::State {
             eprintln!("{}", panic_info);
         }));
 
-        let editor = editor::State::with_stdout(stdout, schedule);
+        let size = crossterm::terminal::size()?;
+        let editor = editor::State::new(stdout, events, size, schedule);
 
         Ok(editor)
     }
@@ -58,12 +56,3 @@ impl EditorLike<editor::State> for editor::State {
         }
     }
 }
-
-impl Drop for editor::State {
-    fn drop(&mut self) {
-        let disable_result = disable_raw_mode();
-        if disable_result.is_err() {
-            eprintln!("could not disable raw mode");
-        }
-    }
-}
@@ -1,17 +1,46 @@
-use crossterm::event::KeyEvent;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    path::PathBuf,
+    time::{Duration as StdDuration, Instant},
+};
 
-use crate::keys::BINDINGS;
+use crate::{dom::activity::ActivityKind, time::Duration};
 
-use super::{Mode, State};
+use super::{registers::Registers, Mode};
+
+/// How long to wait for the next key of a sequence like "gg", before giving up
+/// or running the command bound to the keys so far
+pub const KEY_SEQUENCE_TIMEOUT: StdDuration = StdDuration::from_millis(1000);
+
+/// Counts are capped, so that a mistyped one can not hang the editor or paste
+/// until memory runs out
+pub const MAX_COUNT: usize = 9999;
 
 #[macro_export]
 macro_rules! bind {
     ($key_code:expr, $modifiers:expr, $cmd:expr, $filter:expr) => {
         Binding {
-            key: KeyEvent {
+            keys: std::borrow::Cow::Borrowed(&[KeyEvent {
                 code: $key_code,
                 modifiers: $modifiers,
-            },
+            }]),
+            command: $cmd,
+            filter: $filter,
+        }
+    };
+}
+
+/// Binds a sequence of characters typed one after another, e.g. `['g', 'g']`.
+#[macro_export]
+macro_rules! bind_seq {
+    ([$($key:expr),+], $cmd:expr, $filter:expr) => {
+        Binding {
+            keys: std::borrow::Cow::Borrowed(&[$(KeyEvent {
+                code: KeyCode::Char($key),
+                modifiers: KeyModifiers::NONE,
+            }),+]),
             command: $cmd,
             filter: $filter,
         }
@@ -25,15 +54,24 @@ macro_rules! bind_key {
     };
 }
 
-#[derive(Debug, Clone)]
+/// A sequence of commands known at compile time, e.g. `multi![Command::Save,
+/// Command::Quit]`.
+#[macro_export]
+macro_rules! multi {
+    ($($cmd:expr),+ $(,)?) => {
+        $crate::editor::command::Command::Multi(std::borrow::Cow::Borrowed(&[$($cmd),+]))
+    };
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Command {
     Quit,
     MoveCursor(MoveCursor),
     InsertMode,
     CursorMode,
     TimeMode,
-    GoToMode,
-    DeleteMode,
+    /// Starts selecting lines from the one under the cursor
+    VisualMode,
     InsertTimeBoxBelow,
     InsertTimeBoxAbove,
     ToggleCrossOver,
@@ -43,28 +81,105 @@ pub enum Command {
     PasteBelow,
     MoveTimeCursor,
     SwapTimeSubMode,
-    AdjustTime { hours: i8, minutes: i8 },
+    AdjustTime {
+        hours: i8,
+        minutes: i8,
+    },
     DeleteTime,
     GoToColumn(ColumnKind),
+    /// Deletes the text between the cursor and where a motion on the same
+    /// line would take it, e.g. the word before the cursor
+    DeleteTo(MoveCursor),
+    /// Cuts the line under cursor, or as many lines as given by count
     CutCurrentLine,
+    /// Cuts the line under cursor and count lines (1 by default) above or below
+    CutLines(Dir),
+    /// Copies the line under cursor, or as many lines as given by count
+    YankCurrentLine,
+    /// Copies the line under cursor and count lines (1 by default) above or
+    /// below
+    YankLines(Dir),
+    /// Copies the whole schedule into given register, or the one typed before
+    /// the command
+    YankAll(Option<char>),
     OpenCommandInput,
-    Multi(&'static [Command]),
+    Save,
+    SaveAs(PathBuf),
+    /// Saves, and quits only if saving worked
+    SaveAndQuit,
+    Edit(PathBuf),
+    Undo,
+    Redo,
+    /// Shifts times relatively, from given line or from the cursor onwards
+    ShiftTimes {
+        duration: Duration,
+        from: Option<usize>,
+    },
+    Sort,
+    Set(Setting),
+    /// Starts typing a search
+    Search,
+    ConfirmSearch,
+    /// Stops typing a search, returning to where it started
+    CancelSearch,
+    SearchNext,
+    SearchPrevious,
+    /// Move the cursor to the item at the current time
+    JumpToNow,
+    /// Move the item under the cursor a line up or down, optionally leaving
+    /// the times in place so that only the activities trade places
+    MoveTimeBox {
+        dir: Dir,
+        keep_times: bool,
+    },
+    /// Move the first item not done to the current time, and shift the rest
+    /// of the day with it
+    CatchUp,
+    SetActivityKind(ActivityKind),
+    Multi(Cow<'static, [Command]>),
 }
 
-#[derive(Debug, Clone)]
+/// Options changed with `:set`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Setting {
+    /// Default length of new spans
+    SpanLength(Duration),
+    /// Gaps between items longer than this are pointed out
+    MaxGap(Duration),
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum MoveCursor {
     Dir(Dir),
+    /// First line, or the line given by count
     Top,
+    /// Last line, or the line given by count
     Bottom,
+    /// A screenful up
+    PageUp,
+    /// A screenful down
+    PageDown,
+    /// Start of the next word, continuing on the next line
+    WordForward,
+    /// Start of the previous word, continuing on the previous line
+    WordBackward,
+    /// End of the next word, continuing on the next line
+    WordEnd,
+    /// First column
+    LineStart,
+    /// Last character
+    LineEnd,
+    /// First non-blank character
+    FirstNonBlank,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ColumnKind {
     Index(usize),
     Last,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Dir {
     Up,
     Down,
@@ -72,7 +187,7 @@ pub enum Dir {
     Right,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Filter {
     Mode(Mode),
     Modes(&'static [Mode]),
@@ -89,28 +204,296 @@ impl Filter {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Binding {
-    pub key: KeyEvent,
+    /// The keys pressed one after another, usually only one
+    pub keys: Cow<'static, [KeyEvent]>,
     pub command: Command,
     pub filter: Filter,
 }
 
-impl Binding {
-    fn match_command(&self, key: &KeyEvent, mode: &Mode) -> Option<Command> {
-        if self.filter.match_mode(mode) && key == &self.key {
-            Some(self.command.clone())
-        } else {
-            None
-        }
+impl Command {
+    /// True if the command uses the count typed before it, e.g. "3dd" cuts 3
+    /// lines. Other commands are repeated count times instead.
+    pub fn takes_count(&self) -> bool {
+        matches!(
+            self,
+            Command::CutCurrentLine
+                | Command::CutLines(_)
+                | Command::YankCurrentLine
+                | Command::YankLines(_)
+                | Command::MoveCursor(MoveCursor::Top)
+                | Command::MoveCursor(MoveCursor::Bottom)
+        )
     }
 }
 
-impl Command {
-    /// None -> Command::Noop.
-    pub fn map(key_event: KeyEvent, editor: &State) -> Option<Command> {
-        BINDINGS
+/// Bindings arranged by their key sequences
+#[derive(Debug, Clone, Default)]
+pub struct KeyTrie {
+    /// Bindings ending at this node, the first matching one wins
+    commands: Vec<(Filter, Command)>,
+    children: HashMap<KeyEvent, KeyTrie>,
+}
+
+/// Keys typed so far of an unfinished sequence, e.g. "d3" of "d3j"
+#[derive(Debug, Default)]
+pub struct PendingKeys {
+    pub keys: Vec<KeyEvent>,
+    pub count: Option<usize>,
+    /// Register named for the command, e.g. 'a' of "ayy
+    pub register: Option<char>,
+    /// '"' was typed, the register name comes next
+    awaiting_register: bool,
+    /// When the last key was pressed
+    since: Option<Instant>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum KeyMatch {
+    /// A complete sequence, with the count and the register typed with it
+    Command(Command, Option<usize>, Option<char>),
+    /// The sequence may continue with the next key
+    Pending,
+    /// No binding for these keys. In insert and search mode they are typed as
+    /// text.
+    Unbound(Vec<KeyEvent>),
+}
+
+impl KeyTrie {
+    pub fn new(bindings: &[Binding]) -> KeyTrie {
+        let mut root = KeyTrie::default();
+        for binding in bindings {
+            let node = binding.keys.iter().fold(&mut root, |node, key| {
+                node.children.entry(*key).or_default()
+            });
+            node.commands
+                .push((binding.filter.clone(), binding.command.clone()));
+        }
+        root
+    }
+
+    fn find(&self, keys: &[KeyEvent]) -> Option<&KeyTrie> {
+        keys.iter()
+            .try_fold(self, |node, key| node.children.get(key))
+    }
+
+    fn command(&self, mode: &Mode) -> Option<&Command> {
+        self.commands
             .iter()
-            .find_map(|binding| binding.match_command(&key_event, &editor.mode.borrow()))
+            .find(|(filter, _)| filter.match_mode(mode))
+            .map(|(_, cmd)| cmd)
     }
+
+    /// True if any longer sequence starting from here is bound in given mode
+    fn continues(&self, mode: &Mode) -> bool {
+        self.children
+            .values()
+            .any(|child| child.command(mode).is_some() || child.continues(mode))
+    }
+
+    /// Feeds the next key of a sequence. Digits typed outside insert mode
+    /// form a count, e.g. "5j" or "d3j", and '"' with a name before the
+    /// sequence a register, e.g. "ayy.
+    pub fn feed(&self, pending: &mut PendingKeys, key: KeyEvent, mode: &Mode) -> KeyMatch {
+        pending.since = Some(Instant::now());
+
+        if !mode.types_text() && pending.keys.is_empty() {
+            if pending.awaiting_register {
+                pending.awaiting_register = false;
+                return match key.code {
+                    KeyCode::Char(c) if Registers::is_valid(c) => {
+                        pending.register = Some(c);
+                        KeyMatch::Pending
+                    }
+                    _ => KeyMatch::Unbound(pending.take()),
+                };
+            }
+            let chord = KeyModifiers::CONTROL | KeyModifiers::ALT;
+            if key.code == KeyCode::Char('"') && !key.modifiers.intersects(chord) {
+                pending.awaiting_register = true;
+                return KeyMatch::Pending;
+            }
+        }
+
+        if !mode.types_text() && key.modifiers == KeyModifiers::NONE {
+            if let KeyCode::Char(c) = key.code {
+                // A leading zero is not a count, it may be bound to a command
+                if let Some(digit) = c.to_digit(10).filter(|d| *d > 0 || pending.count.is_some()) {
+                    let count = pending.count.unwrap_or(0);
+                    pending.count = Some((count * 10 + digit as usize).min(MAX_COUNT));
+                    return KeyMatch::Pending;
+                }
+            }
+        }
+
+        pending.keys.push(key);
+        match self.find(&pending.keys) {
+            Some(node) if node.continues(mode) => KeyMatch::Pending,
+            Some(node) => match node.command(mode) {
+                Some(cmd) => {
+                    let cmd = cmd.clone();
+                    let (count, register) = (pending.count, pending.register);
+                    pending.clear();
+                    KeyMatch::Command(cmd, count, register)
+                }
+                None => KeyMatch::Unbound(pending.take()),
+            },
+            None => KeyMatch::Unbound(pending.take()),
+        }
+    }
+
+    /// Called when no key was pressed in time to continue the sequence. Runs
+    /// the command of the keys so far, if any, e.g. "g" when "g" and "gg" are
+    /// both bound.
+    pub fn time_out(&self, pending: &mut PendingKeys, mode: &Mode) -> KeyMatch {
+        let cmd = self
+            .find(&pending.keys)
+            .and_then(|node| node.command(mode))
+            .cloned();
+        match cmd {
+            Some(cmd) if !pending.keys.is_empty() => {
+                let (count, register) = (pending.count, pending.register);
+                pending.clear();
+                KeyMatch::Command(cmd, count, register)
+            }
+            _ => KeyMatch::Unbound(pending.take()),
+        }
+    }
+}
+
+impl PendingKeys {
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+            && self.count.is_none()
+            && self.register.is_none()
+            && !self.awaiting_register
+    }
+
+    /// Time left until the sequence times out
+    pub fn timeout(&self) -> Option<StdDuration> {
+        if self.is_empty() {
+            return None;
+        }
+        let elapsed = self.since.map(|t| t.elapsed()).unwrap_or_default();
+        Some(KEY_SEQUENCE_TIMEOUT.saturating_sub(elapsed))
+    }
+
+    pub fn clear(&mut self) {
+        self.take();
+    }
+
+    fn take(&mut self) -> Vec<KeyEvent> {
+        self.count = None;
+        self.register = None;
+        self.awaiting_register = false;
+        self.since = None;
+        std::mem::take(&mut self.keys)
+    }
+}
+
+/// Shows keys like they are written in the config, e.g. "3 d" or "ctrl+w".
+/// A register comes first, e.g. "\"a 3 y".
+pub fn display_keys(pending: &PendingKeys) -> String {
+    let mut parts: Vec<String> = vec![];
+    if pending.awaiting_register {
+        parts.push("\"".to_owned());
+    }
+    parts.extend(pending.register.map(|name| format!("\"{}", name)));
+    parts.extend(pending.count.iter().map(|c| c.to_string()));
+    parts.extend(pending.keys.iter().map(display_key));
+    parts.join(" ")
+}
+
+fn display_key(key: &KeyEvent) -> String {
+    let mut s = String::new();
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        s.push_str("ctrl+");
+    }
+    if key.modifiers.contains(KeyModifiers::ALT) {
+        s.push_str("alt+");
+    }
+    match key.code {
+        // Upper case letters already show shift
+        KeyCode::Char(c) => s.push(c),
+        KeyCode::Esc => s.push_str("esc"),
+        KeyCode::Enter => s.push_str("enter"),
+        KeyCode::Tab => s.push_str("tab"),
+        code => s.push_str(&format!("{:?}", code).to_lowercase()),
+    }
+    s
+}
+
+#[test]
+fn key_sequences_with_counts() {
+    use crate::keys::BINDINGS;
+
+    let trie = KeyTrie::new(BINDINGS);
+    let mut pending = PendingKeys::default();
+    let mut type_keys = |keys: &str| {
+        let mut key_match = KeyMatch::Pending;
+        for c in keys.chars() {
+            let modifiers = if c.is_ascii_uppercase() {
+                KeyModifiers::SHIFT
+            } else {
+                KeyModifiers::NONE
+            };
+            let key = KeyEvent::new(KeyCode::Char(c), modifiers);
+            key_match = trie.feed(&mut pending, key, &Mode::Cursor);
+        }
+        key_match
+    };
+
+    assert_eq!(
+        type_keys("gg"),
+        KeyMatch::Command(Command::MoveCursor(MoveCursor::Top), None, None)
+    );
+    assert_eq!(type_keys("d"), KeyMatch::Pending);
+    assert_eq!(
+        type_keys("d"),
+        KeyMatch::Command(Command::CutCurrentLine, None, None)
+    );
+    assert_eq!(
+        type_keys("d3j"),
+        KeyMatch::Command(Command::CutLines(Dir::Down), Some(3), None)
+    );
+    assert_eq!(
+        type_keys("99999999999999999999j"),
+        KeyMatch::Command(
+            Command::MoveCursor(MoveCursor::Dir(Dir::Down)),
+            Some(MAX_COUNT),
+            None
+        )
+    );
+    assert_eq!(
+        type_keys("12G"),
+        KeyMatch::Command(Command::MoveCursor(MoveCursor::Bottom), Some(12), None)
+    );
+    assert_eq!(
+        type_keys("5j"),
+        KeyMatch::Command(
+            Command::MoveCursor(MoveCursor::Dir(Dir::Down)),
+            Some(5),
+            None
+        )
+    );
+    assert_eq!(
+        type_keys("\"a2yy"),
+        KeyMatch::Command(Command::YankCurrentLine, Some(2), Some('a'))
+    );
+    // Unknown sequences are dropped
+    assert!(matches!(type_keys("dx"), KeyMatch::Unbound(keys) if keys.len() == 2));
+
+    // Timing out runs nothing when only a longer sequence is bound
+    let mut pending = PendingKeys::default();
+    trie.feed(
+        &mut pending,
+        KeyEvent::new(KeyCode::Char('g'), KeyModifiers::NONE),
+        &Mode::Cursor,
+    );
+    assert!(matches!(
+        trie.time_out(&mut pending, &Mode::Cursor),
+        KeyMatch::Unbound(_)
+    ));
+    assert!(pending.is_empty());
 }
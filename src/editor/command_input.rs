@@ -1,14 +1,22 @@
 use crossterm::{
     cursor,
     event::{Event, KeyCode},
+    style,
+    terminal::{Clear, ClearType},
     QueueableCommand,
 };
-use std::io::{Stdout, Write};
+use std::{io::Write, path::PathBuf, str::FromStr};
+use thiserror::Error;
 
 use super::{
-    command::Command,
+    command::{Command, Setting},
+    events::EventSource,
+    registers::Registers,
     text_capture::{self, TextCapture},
 };
+use crate::{dom::activity::ActivityKind, time::Duration};
+
+const PROMPT: &str = ":";
 
 /// A modal command input that captures stdin and cursor while it's active.
 #[derive(Debug)]
@@ -24,44 +32,277 @@ impl Default for CommandInput {
     }
 }
 
+/// What stopped capturing the command input
+#[derive(Debug, PartialEq)]
+pub enum Captured {
+    /// The entered text, None if the input was cancelled
+    Done(Option<String>),
+    /// The terminal was resized to given columns and rows. The input is kept,
+    /// so capturing can go on on the new bottom row.
+    Resized(u16, u16),
+}
+
 impl CommandInput {
-    /// Captures stdout and blocks while updating the CommandInput contents based on input.
-    pub fn capture(&mut self, stdout: &mut Stdout) -> crossterm::Result<Option<Command>> {
+    /// Captures output and events and blocks while updating the CommandInput
+    /// contents based on input. The input line is drawn on given row, usually
+    /// the bottom one.
+    pub fn capture<W: Write, E: EventSource>(
+        &mut self,
+        out: &mut W,
+        events: &mut E,
+        row: u16,
+    ) -> crossterm::Result<Captured> {
+        self.draw(row, out)?;
+
         loop {
-            let ev = crossterm::event::read()?;
+            let ev = match events.next_event(None)? {
+                Some(ev) => ev,
+                // No more events
+                None => return Ok(Captured::Done(None)),
+            };
             let redraw = match ev {
-                Event::Key(k) => {
+                Event::Key(k) => match k.code {
                     // Enter breaks out of command input
-                    if k.code == KeyCode::Enter {
-                        break;
+                    KeyCode::Enter => break,
+                    // Esc and backspace on empty input cancel
+                    KeyCode::Esc => return Ok(Captured::Done(None)),
+                    KeyCode::Backspace if self.cur_input.text().is_empty() => {
+                        return Ok(Captured::Done(None))
                     }
+                    _ => self.cur_input.input(&k).0,
+                },
+                Event::Mouse(_) => false,
+                Event::Resize(columns, rows) => return Ok(Captured::Resized(columns, rows)),
+            };
 
-                    let (redraw, cursor_move) = self.cur_input.input(&k);
-
-                    use std::cmp::Ordering;
-                    match cursor_move.cmp(&0) {
-                        Ordering::Greater => stdout
-                            .queue(cursor::MoveRight(cursor_move as u16))?
-                            .flush()?,
-                        Ordering::Equal => {}
-                        Ordering::Less => stdout
-                            .queue(cursor::MoveLeft((-cursor_move) as u16))?
-                            .flush()?,
-                    }
+            if redraw {
+                self.draw(row, out)?;
+            }
+        }
+
+        let text = self.cur_input.text().trim().to_owned();
+        Ok(Captured::Done(if text.is_empty() {
+            None
+        } else {
+            Some(text)
+        }))
+    }
+
+    /// Draws the prompt and the input on given row, and places the terminal
+    /// cursor at the input cursor.
+    fn draw<W: Write>(&self, row: u16, out: &mut W) -> crossterm::Result<()> {
+        out.queue(cursor::MoveTo(0, row))?
+            .queue(Clear(ClearType::CurrentLine))?
+            .queue(style::Print(PROMPT))?
+            .queue(style::Print(&*self.cur_input.text()))?
+            .queue(cursor::MoveTo(
+                PROMPT.len() as u16 + self.cur_input.cursor_x(),
+                row,
+            ))?
+            .flush()?;
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum CommandParseError {
+    #[error("not an editor command: {0}")]
+    UnknownCommand(String),
+    #[error("{command}: missing {argument}")]
+    MissingArgument {
+        command: &'static str,
+        argument: &'static str,
+    },
+    #[error("trailing characters: {0}")]
+    TrailingArgument(String),
+    #[error("invalid duration {0}")]
+    InvalidDuration(String),
+    #[error("invalid line number: {0}")]
+    InvalidLine(String),
+    #[error("unknown option: {0}")]
+    UnknownSetting(String),
+    #[error("invalid register: {0}")]
+    InvalidRegister(String),
+    #[error("unknown activity kind: {0}")]
+    UnknownActivityKind(String),
+}
 
-                    redraw
+impl FromStr for Command {
+    type Err = CommandParseError;
+
+    /// Parses an ex-style command line without the leading ':', e.g. "w
+    /// today.md" or "shift +0:30 from 3".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut args = s.split_whitespace();
+        let name = args.next().unwrap_or_default();
+
+        let cmd = match name {
+            "w" | "write" => match args.next() {
+                Some(file) => Command::SaveAs(PathBuf::from(file)),
+                None => Command::Save,
+            },
+            "q" | "quit" => Command::Quit,
+            "wq" | "x" => Command::SaveAndQuit,
+            "e" | "edit" => Command::Edit(PathBuf::from(args.next().ok_or(
+                CommandParseError::MissingArgument {
+                    command: "edit",
+                    argument: "file name",
+                },
+            )?)),
+            "shift" => {
+                let duration = parse_duration(args.next(), "shift")?;
+                let from = match args.next() {
+                    Some("from") => {
+                        let line = args.next().ok_or(CommandParseError::MissingArgument {
+                            command: "shift",
+                            argument: "line number",
+                        })?;
+                        // Lines are numbered from 1 onwards
+                        match line.parse::<usize>() {
+                            Ok(n) if n > 0 => Some(n - 1),
+                            _ => return Err(CommandParseError::InvalidLine(line.to_owned())),
+                        }
+                    }
+                    Some(other) => {
+                        return Err(CommandParseError::TrailingArgument(other.to_owned()))
+                    }
+                    None => None,
+                };
+                Command::ShiftTimes { duration, from }
+            }
+            "sort" => Command::Sort,
+            "catchup" => Command::CatchUp,
+            "y" | "yank" => Command::YankAll(match args.next() {
+                Some(name) => {
+                    let mut chars = name.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) if Registers::is_valid(c) => Some(c),
+                        _ => return Err(CommandParseError::InvalidRegister(name.to_owned())),
+                    }
                 }
-                Event::Mouse(_) => false,
-                Event::Resize(_, _) => false,
-            };
+                None => None,
+            }),
+            "kind" => Command::SetActivityKind(parse_activity_kind(args.next())?),
+            "set" => match args.next() {
+                Some("span") => Command::Set(Setting::SpanLength(parse_positive_duration(
+                    args.next(),
+                    "set span",
+                )?)),
+                Some("gap") => Command::Set(Setting::MaxGap(parse_positive_duration(
+                    args.next(),
+                    "set gap",
+                )?)),
+                Some(other) => return Err(CommandParseError::UnknownSetting(other.to_owned())),
+                None => {
+                    return Err(CommandParseError::MissingArgument {
+                        command: "set",
+                        argument: "option",
+                    })
+                }
+            },
+            _ => return Err(CommandParseError::UnknownCommand(name.to_owned())),
+        };
 
-            if redraw {}
+        match args.next() {
+            Some(trailing) => Err(CommandParseError::TrailingArgument(trailing.to_owned())),
+            None => Ok(cmd),
         }
+    }
+}
+
+fn parse_duration(arg: Option<&str>, command: &'static str) -> Result<Duration, CommandParseError> {
+    let arg = arg.ok_or(CommandParseError::MissingArgument {
+        command,
+        argument: "duration",
+    })?;
+    Duration::from_str(arg).map_err(CommandParseError::InvalidDuration)
+}
+
+/// A duration longer than zero, e.g. the length of a span
+fn parse_positive_duration(
+    arg: Option<&str>,
+    command: &'static str,
+) -> Result<Duration, CommandParseError> {
+    let duration = parse_duration(arg, command)?;
+    if duration.num_minutes() <= 0 {
+        return Err(CommandParseError::InvalidDuration(
+            arg.unwrap_or_default().to_owned(),
+        ));
+    }
+    Ok(duration)
+}
 
-        Ok(input_into_command(&self.cur_input.text()))
+/// One of the kinds written before a summary, e.g. "meal", or "unknown" for
+/// none
+fn parse_activity_kind(arg: Option<&str>) -> Result<ActivityKind, CommandParseError> {
+    match arg {
+        Some("meal") => Ok(ActivityKind::Meal),
+        Some("sprint") => Ok(ActivityKind::Sprint),
+        Some("unknown") => Ok(ActivityKind::Unknown),
+        Some(other) => Err(CommandParseError::UnknownActivityKind(other.to_owned())),
+        None => Err(CommandParseError::MissingArgument {
+            command: "kind",
+            argument: "activity kind",
+        }),
     }
 }
 
-fn input_into_command(_text: &str) -> Option<Command> {
-    None
+#[test]
+fn command_grammar() {
+    assert_eq!(Command::from_str("w"), Ok(Command::Save));
+    assert_eq!(
+        Command::from_str("w data/today.md"),
+        Ok(Command::SaveAs(PathBuf::from("data/today.md")))
+    );
+    assert_eq!(
+        Command::from_str("shift +0:30 from 3"),
+        Ok(Command::ShiftTimes {
+            duration: Duration::hm(0, 30),
+            from: Some(2),
+        })
+    );
+    assert_eq!(
+        Command::from_str("shift -1:15"),
+        Ok(Command::ShiftTimes {
+            duration: Duration::hm(-1, -15),
+            from: None,
+        })
+    );
+    assert_eq!(
+        Command::from_str("set span 2:00"),
+        Ok(Command::Set(Setting::SpanLength(Duration::hm(2, 0))))
+    );
+    assert_eq!(
+        Command::from_str("set span -1:00"),
+        Err(CommandParseError::InvalidDuration("-1:00".to_owned()))
+    );
+    assert_eq!(
+        Command::from_str("set gap 0:00"),
+        Err(CommandParseError::InvalidDuration("0:00".to_owned()))
+    );
+    assert_eq!(
+        Command::from_str("shift +0:30 from 0"),
+        Err(CommandParseError::InvalidLine("0".to_owned()))
+    );
+    assert_eq!(
+        Command::from_str("q!"),
+        Err(CommandParseError::UnknownCommand("q!".to_owned()))
+    );
+    assert_eq!(Command::from_str("yank +"), Ok(Command::YankAll(Some('+'))));
+    assert_eq!(
+        Command::from_str("kind meal"),
+        Ok(Command::SetActivityKind(ActivityKind::Meal))
+    );
+    assert_eq!(
+        Command::from_str("kind lunch"),
+        Err(CommandParseError::UnknownActivityKind("lunch".to_owned()))
+    );
+    assert_eq!(
+        Command::from_str("yank 1"),
+        Err(CommandParseError::InvalidRegister("1".to_owned()))
+    );
+    assert_eq!(
+        Command::from_str("sort now"),
+        Err(CommandParseError::TrailingArgument("now".to_owned()))
+    );
 }
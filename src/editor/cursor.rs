@@ -1,23 +1,19 @@
-use std::{
-    cell::RefCell,
-    convert::TryFrom,
-    io::{Stdout, Write},
-    rc::Rc,
-};
-
-use crate::editor::Result;
+use std::{cell::RefCell, convert::TryFrom, io::Write, rc::Rc};
+
+use crate::editor::{graphemes, viewport::Viewport, Result};
 use crossterm::{cursor, QueueableCommand};
 
 use crate::schedule::Schedule;
 
 #[derive(Debug)]
 pub struct ContentCursor {
-    // Ghost position of the cursor
+    /// Column on screen the cursor was last moved to horizontally, kept when
+    /// moving between lines of different lengths
     hghost: u16,
-    /// The position of the cursor on terminal
+    /// The position of the cursor on terminal. Tracked here instead of asking
+    /// the terminal, the terminal cursor is moved here on redraw.
     pub pos: TerminalPos,
-    schedule_y: Rc<RefCell<u16>>,
-    schedule_h: Rc<RefCell<u16>>,
+    viewport: Rc<RefCell<Viewport>>,
 }
 
 /// Cursor position in absolute terminal coordinates
@@ -36,170 +32,164 @@ pub struct MappedPos {
 
 impl ContentCursor {
     pub fn create_at_top_left(
-        schedule_y: Rc<RefCell<u16>>,
-        schedule_h: Rc<RefCell<u16>>,
-        stdout: &mut Stdout,
+        viewport: Rc<RefCell<Viewport>>,
         schedule: &Schedule,
     ) -> ContentCursor {
+        viewport.borrow_mut().offset = 0;
         let pos = MappedPos::first()
-            .map_to_terminal(*schedule_y.borrow(), *schedule_h.borrow(), schedule)
+            .map_to_terminal(&viewport.borrow(), schedule)
             .unwrap();
 
-        Self::create_at(pos, schedule_y, schedule_h, stdout)
+        Self::create_at(pos, viewport)
     }
-    pub fn create_at(
-        pos: TerminalPos,
-        schedule_y: Rc<RefCell<u16>>,
-        schedule_h: Rc<RefCell<u16>>,
-        stdout: &mut Stdout,
-    ) -> ContentCursor {
+    pub fn create_at(pos: TerminalPos, viewport: Rc<RefCell<Viewport>>) -> ContentCursor {
         let hghost = pos.hpos;
 
-        // Move the cursor to the start of the schedule
-        Self::move_terminal_cursor(pos.hpos, pos.vpos + *schedule_y.borrow(), stdout).unwrap();
-
         ContentCursor {
             hghost,
             pos,
-            schedule_y,
-            schedule_h,
+            viewport,
         }
     }
 
     /// This method may panic, if called for an invalid content cursor
     pub fn map_to_content(&self, schedule: &Schedule) -> MappedPos {
-        let y = *self.schedule_y.borrow();
-        let h = *self.schedule_h.borrow();
         self.pos
-            .map_to_content(y, h, schedule)
+            .map_to_content(&self.viewport.borrow(), schedule)
             .expect("failed to map cursor to content")
     }
 
     /// This method may panic, if called for an invalid content cursor
     pub fn map_to_line(&self) -> usize {
-        let y = *self.schedule_y.borrow();
-        let h = *self.schedule_h.borrow();
-
-        TerminalPos::map_to_line(self.pos.vpos, y, h).expect("failed to map cursor to content")
+        TerminalPos::map_to_line(self.pos.vpos, &self.viewport.borrow())
+            .expect("failed to map cursor to content")
     }
 
-    pub fn redraw(&mut self, stdout: &mut Stdout) -> Result<()> {
-        Self::move_terminal_cursor(self.pos.hpos, self.pos.vpos, stdout)
+    /// Moves the terminal cursor where this cursor is
+    pub fn redraw<W: Write>(&self, out: &mut W) -> Result<()> {
+        Ok(out
+            .queue(cursor::MoveTo(self.pos.hpos, self.pos.vpos))?
+            .flush()?)
     }
 
     /// Returns true if cursor was moved
-    pub fn move_down(&mut self, schedule: &Schedule, stdout: &mut Stdout) -> Result<bool> {
-        self.move_cursor_mapped((0, 1), schedule, stdout)
+    pub fn move_down(&mut self, schedule: &Schedule) -> bool {
+        self.move_cursor_mapped((0, 1), schedule)
     }
 
     /// Returns true if cursor was moved
-    pub fn move_up(&mut self, schedule: &Schedule, stdout: &mut Stdout) -> Result<bool> {
-        self.move_cursor_mapped((0, -1), schedule, stdout)
+    pub fn move_up(&mut self, schedule: &Schedule) -> bool {
+        self.move_cursor_mapped((0, -1), schedule)
     }
 
     /// Returns true if cursor was moved
-    pub fn move_left(&mut self, schedule: &Schedule, stdout: &mut Stdout) -> Result<bool> {
-        self.move_cursor_mapped((-1, 0), schedule, stdout)
+    pub fn move_left(&mut self, schedule: &Schedule) -> bool {
+        self.move_cursor_mapped((-1, 0), schedule)
     }
 
     /// Returns true if cursor was moved
-    pub fn move_right(&mut self, schedule: &Schedule, stdout: &mut Stdout) -> Result<bool> {
-        self.move_cursor_mapped((1, 0), schedule, stdout)
+    pub fn move_right(&mut self, schedule: &Schedule) -> bool {
+        self.move_cursor_mapped((1, 0), schedule)
     }
 
-    fn move_cursor_mapped(
-        &mut self,
-        delta: (i16, i16),
-        schedule: &Schedule,
-        stdout: &mut Stdout,
-    ) -> Result<bool> {
-        // Get current physical position of the cursor on the terminal screen
-        let cur_pos: TerminalPos = cursor::position()?.into();
-
-        // Figure out where it lands on the schedule
-        let mapped_pos = match cur_pos.map_to_content(
-            *self.schedule_y.borrow(),
-            *self.schedule_h.borrow(),
-            schedule,
-        ) {
+    fn move_cursor_mapped(&mut self, delta: (i16, i16), schedule: &Schedule) -> bool {
+        let mapped_pos = match self.pos.map_to_content(&self.viewport.borrow(), schedule) {
             Some(pos) => pos,
             // OOB
-            None => {
-                // Check if we have a valid ghost on vertical move
-                if delta.1.abs() >= 0 && delta.0 == 0 {
-                    match TerminalPos::new(self.hghost, cur_pos.vpos).map_to_content(
-                        *self.schedule_y.borrow(),
-                        *self.schedule_h.borrow(),
-                        schedule,
-                    ) {
-                        Some(pos) => pos,
-                        None => return Ok(false),
-                    }
-                } else {
-                    return Ok(false);
-                }
-            }
+            None => return false,
         };
 
+        // Vertical moves keep to the ghost column
+        if delta.0 == 0 {
+            return match usize::try_from(mapped_pos.line as i16 + delta.1) {
+                Ok(line) => self.move_to_line(line, schedule),
+                Err(_) => false,
+            };
+        }
+
         // Move the cursor using the delta
         let n_mapped_x = if let Ok(x) = u16::try_from(mapped_pos.col as i16 + delta.0) {
             x
         } else {
-            return Ok(false);
+            return false;
         };
-        let n_mapped_y = if let Ok(y) = u16::try_from(mapped_pos.line as i16 + delta.1) {
-            y
-        } else {
-            return Ok(false);
+
+        self.move_to_content(
+            &MappedPos::new(n_mapped_x, mapped_pos.line as u16),
+            schedule,
+        )
+    }
+
+    /// Moves to given line, to the column on screen where the cursor was last
+    /// moved horizontally, or to the end of a shorter line. Returns true if
+    /// cursor was moved.
+    pub fn move_to_line(&mut self, line: usize, schedule: &Schedule) -> bool {
+        let summary = match schedule.timeboxes.get(line) {
+            Some(time_box) => &time_box.activity.summary,
+            None => return false,
         };
+        let ghost = self.hghost;
+        let x = (ghost as usize).saturating_sub(schedule.time_col_width() + 1);
+        let col = graphemes::col_at(summary, x).min(graphemes::count(summary));
 
-        self.move_to_content(&MappedPos::new(n_mapped_x, n_mapped_y), schedule, stdout)
+        let moved = self.move_to_content(&MappedPos::new(col, line), schedule);
+        self.hghost = ghost;
+        moved
     }
 
     /// Move to content column
-    pub fn move_to_column(
-        &mut self,
-        idx: usize,
-        schedule: &Schedule,
-        stdout: &mut Stdout,
-    ) -> Result<bool> {
+    pub fn move_to_column(&mut self, idx: usize, schedule: &Schedule) -> bool {
         let cursor_line = self.map_to_line();
 
-        self.move_to_content(&MappedPos::new(idx, cursor_line), schedule, stdout)
+        self.move_to_content(&MappedPos::new(idx, cursor_line), schedule)
     }
 
-    pub fn move_to_content(
-        &mut self,
-        mapped_pos: &MappedPos,
-        schedule: &Schedule,
-        stdout: &mut Stdout,
-    ) -> Result<bool> {
+    /// Returns true if cursor was moved
+    pub fn move_to_content(&mut self, mapped_pos: &MappedPos, schedule: &Schedule) -> bool {
+        // Scroll the line into view
+        if mapped_pos.line < schedule.timeboxes.len() {
+            self.viewport.borrow_mut().scroll_to(mapped_pos.line);
+        }
+
         // Restore screen position by mapping the content to screen
-        let n_cur_pos = match mapped_pos.map_to_terminal(
-            *self.schedule_y.borrow(),
-            *self.schedule_h.borrow(),
-            schedule,
-        ) {
+        let n_cur_pos = match mapped_pos.map_to_terminal(&self.viewport.borrow(), schedule) {
             Some(pos) => pos,
-            None => return Ok(false),
+            None => return false,
         };
         self.pos = n_cur_pos;
+        self.hghost = n_cur_pos.hpos;
 
-        // Fail violently if the final move fails
-        Self::move_terminal_cursor(n_cur_pos.hpos, n_cur_pos.vpos, stdout).expect("cursor desync");
+        true
+    }
 
-        Ok(true)
+    /// Keeps the cursor on given line after the viewport has changed, e.g.
+    /// when the terminal was resized
+    pub fn relayout(&mut self, line: usize, schedule: &Schedule) {
+        let line = line.min(schedule.timeboxes.len() - 1);
+        {
+            let mut viewport = self.viewport.borrow_mut();
+            viewport.scroll_to(line);
+            self.pos.vpos = viewport.line_to_row(line).expect("line was scrolled to");
+        }
+        self.clamp_to_content(schedule);
     }
 
     pub fn clamp_to_content(&mut self, schedule: &Schedule) {
-        let y_min = *self.schedule_y.borrow();
-        if self.pos.vpos < y_min {
-            self.pos.vpos = y_min;
-        }
+        let last_line = schedule.timeboxes.len() - 1;
+        let line = {
+            let viewport = self.viewport.borrow();
+            TerminalPos::map_to_line(self.pos.vpos, &viewport)
+                .unwrap_or(viewport.offset)
+                .min(last_line)
+        };
 
-        let y_max = y_min + (*self.schedule_h.borrow()).min(schedule.timeboxes.len() as u16 - 1);
-        if self.pos.vpos > y_max {
-            self.pos.vpos = y_max;
+        // Keep the line in view, scrolling back if lines were removed from the
+        // end
+        {
+            let mut viewport = self.viewport.borrow_mut();
+            viewport.clamp(schedule.timeboxes.len());
+            viewport.scroll_to(line);
+            self.pos.vpos = viewport.line_to_row(line).expect("line was scrolled to");
         }
 
         let x_min = schedule.time_col_width() as u16 + 1;
@@ -208,14 +198,10 @@ impl ContentCursor {
         }
 
         let content_line = self.map_to_line();
-        let x_max = x_min + schedule.timeboxes[content_line].activity.summary.len() as u16;
+        let x_max =
+            x_min + graphemes::width(&schedule.timeboxes[content_line].activity.summary) as u16;
         if self.pos.hpos > x_max {
             self.pos.hpos = x_max;
         }
     }
-
-    /// Makes the API call to crossterm, moving the cursor to target terminal position.
-    fn move_terminal_cursor(screen_h: u16, screen_v: u16, stdout: &mut Stdout) -> Result<()> {
-        Ok(stdout.queue(cursor::MoveTo(screen_h, screen_v))?.flush()?)
-    }
 }
@@ -1,6 +1,10 @@
 use crate::schedule::Schedule;
 
-use super::cursor::{MappedPos, TerminalPos};
+use super::{
+    cursor::{MappedPos, TerminalPos},
+    graphemes,
+    viewport::Viewport,
+};
 
 impl TerminalPos {
     pub fn new<U16>(hpos: U16, vpos: U16) -> TerminalPos
@@ -13,30 +17,27 @@ impl TerminalPos {
         }
     }
 
-    /// Maps cursor to content (0, 0) being top-left of screen. Returns None on
-    /// out-of-bounds.
-    pub fn map_to_content(
-        &self,
-        render_y: u16,
-        render_height: u16,
-        schedule: &Schedule,
-    ) -> Option<MappedPos> {
-        let line_idx = match Self::map_to_line(self.vpos, render_y, render_height) {
-            Some(idx) => idx,
-            None => return None,
-        };
+    /// Maps cursor to content (0, 0) being the first line of the schedule,
+    /// which may be scrolled out of view. Returns None on out-of-bounds.
+    pub fn map_to_content(&self, viewport: &Viewport, schedule: &Schedule) -> Option<MappedPos> {
+        let line_idx = Self::map_to_line(self.vpos, viewport)?;
+        if line_idx >= schedule.timeboxes.len() {
+            // Out-of-bounds, below the last line
+            return None;
+        }
 
         // Content == the summary of the activity
         let content_on_line = &schedule.timeboxes[line_idx].activity.summary;
 
         let char_idx = match (self.hpos as usize).checked_sub(schedule.time_col_width() + 1) {
-            Some(char_idx) => {
-                if char_idx >= content_on_line.chars().count() + 2 {
+            Some(x) => {
+                if x >= graphemes::width(content_on_line) + 2 {
                     // Out-of-bounds, content is leftwards
                     return None;
                 }
 
-                char_idx
+                // Wide characters take two columns on screen
+                graphemes::col_at(content_on_line, x)
             }
             // Out-of-bounds, content is rightwards
             None => return None,
@@ -45,21 +46,10 @@ impl TerminalPos {
         Some(MappedPos::new(char_idx as u16, line_idx as u16))
     }
 
-    /// Maps cursor to line 0 being the topmost line, None on OOB.
-    pub fn map_to_line(cursor_y: u16, render_y: u16, render_height: u16) -> Option<usize> {
-        let line_idx = match cursor_y.checked_sub(render_y) {
-            Some(idx) => {
-                if idx >= render_height {
-                    // Out-of-bounds, content is above
-                    return None;
-                }
-                idx as usize
-            }
-            // Out-of-bounds, content is below
-            None => return None,
-        };
-
-        Some(line_idx)
+    /// Maps cursor to line 0 being the first line of the schedule, None on
+    /// OOB.
+    pub fn map_to_line(cursor_y: u16, viewport: &Viewport) -> Option<usize> {
+        viewport.row_to_line(cursor_y)
     }
 }
 
@@ -89,24 +79,22 @@ impl MappedPos {
 
     /// Uses the content to map the current cursor position into a terminal position.
     /// (0, 0) is the first character on the first item of the day at e.g. terminal
-    /// position (13, 0). Returns None when cursor is out-of-bounds.
-    pub fn map_to_terminal(
-        &self,
-        render_y: u16,
-        render_height: u16,
-        schedule: &Schedule,
-    ) -> Option<TerminalPos> {
-        if self.line as u16 >= render_height {
+    /// position (13, 0) when not scrolled. Returns None when cursor is
+    /// out-of-bounds or scrolled out of view.
+    pub fn map_to_terminal(&self, viewport: &Viewport, schedule: &Schedule) -> Option<TerminalPos> {
+        if self.line >= schedule.timeboxes.len() {
             return None;
         }
-        let out_y = self.line as u16 + render_y;
+        let out_y = viewport.line_to_row(self.line)?;
 
         let content_on_line = &schedule.timeboxes[self.line].activity.summary;
-        if self.col > content_on_line.chars().count() {
+        if self.col > graphemes::count(content_on_line) {
             return None;
         }
 
-        let out_x = self.col as u16 + schedule.time_col_width() as u16 + 1;
+        let out_x = graphemes::width_before(content_on_line, self.col) as u16
+            + schedule.time_col_width() as u16
+            + 1;
 
         Some(TerminalPos::new(out_x, out_y))
     }
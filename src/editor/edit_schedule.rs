@@ -1,74 +1,48 @@
-use std::io::Stdout;
-
 use crossterm::event::{KeyEvent, KeyModifiers};
+use std::ops::{Range, RangeInclusive};
 
 use crate::{
+    diagnostics,
     dom::{timebox::AdjustPolicy, TimeBox},
-    editor::{cursor::ContentCursor, Result},
+    editor::{cursor::ContentCursor, graphemes, Result},
     schedule::Schedule,
-    time::Duration,
+    time::{Duration, Time},
 };
 
 impl Schedule {
     /// Changes the content based on the input key. Returns true if something was changed.
-    pub fn edit_content(
-        &mut self,
-        key: &KeyEvent,
-        cursor: &mut ContentCursor,
-        stdout: &mut Stdout,
-    ) -> Result<bool> {
+    pub fn edit_content(&mut self, key: &KeyEvent, cursor: &mut ContentCursor) -> Result<bool> {
         let KeyEvent { code, modifiers } = key;
 
-        let pos = cursor.map_to_content(&self);
+        let pos = cursor.map_to_content(self);
 
         let edit_text = &mut self.timeboxes[pos.line].activity.summary;
-        let char_idx = pos.col;
+        let col = pos.col;
 
         use crossterm::event::KeyCode;
         let redraw = match code {
             // Insert char and move cursor right
             KeyCode::Char(c) => {
-                // HACK: work around hard to manage multi-codepoint unicode characters
-                let c = if c.len_utf8() != 1 {
-                    match c {
-                        'ä' => 'a',
-                        'ö' => 'o',
-                        // Return on unknown multi-codepoint character
-                        _ => return Ok(false),
-                    }
+                let text = if modifiers.intersects(KeyModifiers::SHIFT) {
+                    c.to_uppercase().collect()
                 } else {
-                    *c
+                    c.to_string()
                 };
 
-                let c = if modifiers.intersects(KeyModifiers::SHIFT) {
-                    c.to_uppercase().next().unwrap()
-                } else {
-                    c
-                };
-
-                edit_text.insert(char_idx, c);
-                // Alternative implementation for insert
-                /*
-                let (start, end) = edit_text.split_at(char_idx);
-                let n_text = format!("{}{}{}", start, c, end);
-                *edit_text = n_text;
-                */
-                cursor.move_right(&self, stdout)?;
+                // A combining character joins the previous grapheme, leaving
+                // the cursor where it was
+                let next_col = graphemes::insert(edit_text, col, &text);
+                cursor.move_to_content(&pos.column(next_col), self);
                 true
             }
             // Remove the character to the left of cursor, then move cursor left
-            KeyCode::Backspace => {
-                if char_idx != 0 {
-                    let remove = char_idx - 1;
-                    edit_text.remove(remove);
+            KeyCode::Backspace if col != 0 => {
+                graphemes::remove(edit_text, col - 1);
 
-                    // Move cursor left
-                    cursor.move_left(&self, stdout)?;
+                // Move cursor left
+                cursor.move_to_content(&pos.column(col - 1), self);
 
-                    true
-                } else {
-                    false
-                }
+                true
             }
             KeyCode::Enter => false,
             KeyCode::Home => false,
@@ -78,17 +52,7 @@ impl Schedule {
             KeyCode::Tab => false,
             KeyCode::BackTab => false,
             // Remove the character right of cursor
-            KeyCode::Delete => {
-                let line_len = edit_text.len();
-                if char_idx != line_len {
-                    let remove = char_idx;
-                    edit_text.remove(remove);
-
-                    true
-                } else {
-                    false
-                }
-            }
+            KeyCode::Delete => graphemes::remove(edit_text, col),
             KeyCode::Insert => false,
             KeyCode::F(_) => false,
             _ => false,
@@ -114,7 +78,7 @@ impl Schedule {
         time_cursor: usize,
     ) {
         // Always adjust the primary index
-        self.adjust_time_absolute(primary_index, adjust_duration, time_cursor);
+        self.timeboxes[primary_index].adjust_absolute(adjust_duration, time_cursor == 0);
 
         // Adjust the others only if they are not fixed
         for idx in primary_index + 1..self.timeboxes.len() {
@@ -125,20 +89,245 @@ impl Schedule {
         }
     }
 
-    /// Adjusts the item at given index. Reorders the item if necessary
+    /// Moves the first item not done that has a time to start at given time,
+    /// and shifts the items after it like `adjust_times_relative`. Returns the
+    /// line of the item and how late it was, None if all timed items are
+    /// done. Nothing moves when now is before the item, the day is ahead of
+    /// schedule then.
+    pub fn catch_up(&mut self, now: &Time) -> Option<(usize, Duration)> {
+        let line = self
+            .timeboxes
+            .iter()
+            .position(|time_box| !time_box.done && time_box.time.is_some())?;
+        let start = self.timeboxes[line].time.as_ref()?.start();
+
+        // Within 12 hours either way, so that running late past midnight is
+        // not mistaken for being early, nor checking the day before wake-up
+        // for running almost a day late
+        let mut minutes = now.minutes_since(start) as i64;
+        if minutes > 12 * 60 {
+            minutes -= 24 * 60;
+        }
+        let delta = Duration::hm((minutes / 60) as i8, (minutes % 60) as i8);
+        if minutes > 0 {
+            self.adjust_times_relative(line, &delta, 0);
+        }
+
+        Some((line, delta))
+    }
+
+    /// Lines of fixed items after line from that overlap another item, or
+    /// are out of order with one
+    pub fn overlapping_fixed(&self, from: usize) -> Vec<usize> {
+        let is_fixed = |line: &usize| {
+            *line > from && self.timeboxes[*line].adjust_policy == AdjustPolicy::Fixed
+        };
+        // Gaps do not matter here
+        let mut lines: Vec<usize> = diagnostics::check(self, &Duration::hours(24))
+            .iter()
+            .filter_map(|diagnostic| Some([diagnostic.line, diagnostic.conflicts_with()?]))
+            .flatten()
+            .filter(is_fixed)
+            .collect();
+        lines.sort_unstable();
+        lines.dedup();
+        lines
+    }
+
+    /// Adjusts the item at given index, then moves it into chronological
+    /// order with the untimed items below it. Returns where it ended up.
     pub fn adjust_time_absolute(
         &mut self,
         idx: usize,
         adjust_duration: &Duration,
         time_cursor: usize,
-    ) {
+    ) -> usize {
         let timebox = &mut self.timeboxes[idx];
-        timebox.adjust_absolute(&adjust_duration, time_cursor == 0);
+        timebox.adjust_absolute(adjust_duration, time_cursor == 0);
+        self.reorder(idx)
+    }
+
+    /// Sorts the time boxes by their start time counting from wake-up, so
+    /// that items past midnight stay at the end of the day. The sort is
+    /// stable, and untimed items stay anchored below their preceding timed
+    /// item. Returns the line each item was on before the sort.
+    pub fn sort(&mut self) -> Vec<usize> {
+        let mut groups = self.groups();
+        groups.sort_by_key(|lines| self.group_start(lines));
+
+        let order: Vec<usize> = groups.into_iter().flatten().collect();
+        let mut timeboxes: Vec<Option<TimeBox>> = self.timeboxes.drain(..).map(Some).collect();
+        self.timeboxes = order
+            .iter()
+            .map(|&line| timeboxes[line].take().expect("each line is moved once"))
+            .collect();
+        order
+    }
+
+    /// Moves the item on given line, with the untimed items below it, before
+    /// the first item starting later, leaving the others in their order.
+    /// Returns the line the item ended up on.
+    pub fn reorder(&mut self, line: usize) -> usize {
+        let groups = self.groups();
+        let group = match groups.iter().find(|lines| lines.contains(&line)) {
+            Some(lines) => lines.clone(),
+            None => return line,
+        };
+        let start = self.group_start(&group);
+        if start.is_none() {
+            // Leading untimed items stay at the top
+            return line;
+        }
+
+        // Where the first group starting later begins, once the item is out
+        let later = groups
+            .iter()
+            .filter(|lines| **lines != group)
+            .find(|lines| self.group_start(lines) > start)
+            .map(|lines| lines.start)
+            .unwrap_or_else(|| self.timeboxes.len());
+        let to = if later > group.start {
+            later - group.len()
+        } else {
+            later
+        };
+
+        let moved: Vec<TimeBox> = self.timeboxes.drain(group.clone()).collect();
+        self.timeboxes.splice(to..to, moved);
+        to + (line - group.start)
+    }
+
+    /// Lines of each timed item with the untimed items following it. Leading
+    /// untimed items form a group of their own.
+    fn groups(&self) -> Vec<Range<usize>> {
+        let mut groups: Vec<Range<usize>> = vec![];
+        for (line, time_box) in self.timeboxes.iter().enumerate() {
+            match groups.last_mut() {
+                Some(lines) if time_box.time.is_none() => lines.end = line + 1,
+                _ => groups.push(line..line + 1),
+            }
+        }
+        groups
+    }
+
+    /// Minutes since wake-up of the first item in a group, None if untimed
+    fn group_start(&self, lines: &Range<usize>) -> Option<u16> {
+        self.timeboxes[lines.start]
+            .time
+            .as_ref()
+            .map(|time| time.start().minutes_since(&self.wake_up))
+    }
+
+    /// Shifts the times of the items on given lines, fixed ones too, leaving
+    /// the rest of the schedule alone
+    pub fn shift_lines(&mut self, lines: RangeInclusive<usize>, duration: &Duration) {
+        for time_box in &mut self.timeboxes[lines] {
+            time_box.adjust_absolute(duration, true);
+        }
     }
 
     pub fn swap(&mut self, first: usize, second: usize) {
-        let temp = self.timeboxes[first].clone();
-        self.timeboxes[first] = self.timeboxes[second].clone();
-        self.timeboxes[second] = temp;
+        self.timeboxes.swap(first, second);
+    }
+
+    /// Swaps the items on two lines but keeps the times, and whether they
+    /// are fixed, on their lines. The activities trade time slots.
+    pub fn swap_activities(&mut self, first: usize, second: usize) {
+        self.swap(first, second);
+        let (first, second) = (first.min(second), first.max(second));
+        let (head, tail) = self.timeboxes.split_at_mut(second);
+        let (a, b) = (&mut head[first], &mut tail[0]);
+        std::mem::swap(&mut a.time, &mut b.time);
+        std::mem::swap(&mut a.adjust_policy, &mut b.adjust_policy);
     }
 }
+
+#[test]
+fn catch_up_to_now() {
+    use std::str::FromStr;
+
+    let mut schedule = Schedule::from_str(
+        "- [x] 08:00 Coffee\n- [ ] 09:00 Work\n- [ ] ! 12:00--13:00 Lunch\n- [ ] 14:00 Walk\n",
+    )
+    .unwrap();
+
+    let (line, delta) = schedule.catch_up(&Time::hm(10, 30)).unwrap();
+    assert_eq!((line, delta), (1, Duration::hm(1, 30)));
+    let starts: Vec<Time> = schedule
+        .timeboxes
+        .iter()
+        .map(|time_box| *time_box.time.as_ref().unwrap().start())
+        .collect();
+    assert_eq!(
+        starts,
+        vec![
+            Time::hm(8, 0),
+            Time::hm(10, 30),
+            Time::hm(12, 0),
+            Time::hm(15, 30)
+        ]
+    );
+    assert!(schedule.overlapping_fixed(line).is_empty());
+
+    // Work now starts after lunch has begun
+    schedule.catch_up(&Time::hm(12, 15)).unwrap();
+    assert_eq!(schedule.overlapping_fixed(1), vec![2]);
+
+    // Being early leaves the day alone
+    let (_, delta) = schedule.catch_up(&Time::hm(11, 0)).unwrap();
+    assert_eq!(delta, Duration::hm(-1, -15));
+    assert_eq!(
+        schedule.timeboxes[1].time.as_ref().unwrap().start(),
+        &Time::hm(12, 15)
+    );
+
+    // Before wake-up is early, not almost a day late
+    let mut schedule =
+        Schedule::from_str("<!-- wake-up: 08:00 -->\n- [ ] 09:00 Work\n- [ ] 12:00 Lunch\n")
+            .unwrap();
+    let (_, delta) = schedule.catch_up(&Time::hm(7, 30)).unwrap();
+    assert_eq!(delta, Duration::hm(-1, -30));
+    assert_eq!(
+        schedule.timeboxes[0].time.as_ref().unwrap().start(),
+        &Time::hm(9, 0)
+    );
+
+    // Running late past midnight
+    let mut schedule = Schedule::from_str("<!-- wake-up: 08:00 -->\n- [ ] 23:00 Read\n").unwrap();
+    let (_, delta) = schedule.catch_up(&Time::hm(0, 30)).unwrap();
+    assert_eq!(delta, Duration::hm(1, 30));
+}
+
+#[test]
+fn sort_and_reorder() {
+    use std::str::FromStr;
+
+    let summaries = |schedule: &Schedule| -> Vec<String> {
+        schedule
+            .timeboxes
+            .iter()
+            .map(|time_box| time_box.activity.summary.clone())
+            .collect()
+    };
+    let mut schedule = Schedule::from_str(
+        "- [ ] Untimed\n- [ ] 01:00 Sleep\n- [ ] 14:00 Walk\n- [ ] Water plants\n- [ ] 09:00 Work\n- [ ] 09:00 Mail\n",
+    )
+    .unwrap();
+    schedule.wake_up = Time::hm(8, 0);
+
+    // Past midnight is the end of the day
+    assert_eq!(schedule.sort(), vec![0, 4, 5, 2, 3, 1]);
+    assert_eq!(
+        summaries(&schedule),
+        vec!["Untimed", "Work", "Mail", "Walk", "Water plants", "Sleep"]
+    );
+
+    // Work is moved after the walk and the plants watered after it
+    let line = schedule.adjust_time_absolute(1, &Duration::hm(6, 0), 0);
+    assert_eq!(line, 4);
+    assert_eq!(
+        summaries(&schedule),
+        vec!["Untimed", "Mail", "Walk", "Water plants", "Work", "Sleep"]
+    );
+    assert_eq!(schedule.adjust_time_absolute(4, &Duration::hm(-7, 0), 0), 1);
+}
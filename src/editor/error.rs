@@ -11,12 +11,14 @@ use crate::template_parsing;
 pub enum Error {
     #[error("crossterm error")]
     Crossterm(#[from] ErrorKind),
-    #[error("I/O error")]
+    #[error("I/O error: {0}")]
     Io(#[from] io::Error),
     #[error("strfmt format error")]
     Strfmt(#[from] strfmt::FmtError),
-    #[error("template parse error")]
+    #[error("parse error: {0}")]
     TemplateParse(#[from] template_parsing::ParseError),
+    #[error("no file to save to")]
+    NoFile,
     #[error("resource ownership error")]
     ResourceOwnership(#[from] ResourceOwnershipError),
 }
@@ -0,0 +1,96 @@
+use crossterm::{
+    event::{self, Event, KeyEvent},
+    terminal::{disable_raw_mode, enable_raw_mode},
+};
+use std::{collections::VecDeque, time::Duration};
+
+use super::keymap::{parse_script, KeymapError};
+
+/// Where the editor reads key presses and resizes from
+pub trait EventSource {
+    /// Waits for the next event, at most until timeout if one is given.
+    /// Returns None if the timeout passed first, or without a timeout if
+    /// there will be no more events.
+    fn next_event(&mut self, timeout: Option<Duration>) -> crossterm::Result<Option<Event>>;
+
+    /// True if there will be no more events, so that the editor can stop
+    /// instead of waiting for the clock to tick
+    fn is_finished(&self) -> bool {
+        false
+    }
+}
+
+/// Events from the terminal. Keeps the terminal in raw mode while alive.
+#[derive(Debug)]
+pub struct TerminalEvents(());
+
+impl TerminalEvents {
+    /// Enables raw mode, which is disabled again on drop
+    pub fn raw() -> crossterm::Result<TerminalEvents> {
+        enable_raw_mode()?;
+        Ok(TerminalEvents(()))
+    }
+}
+
+impl EventSource for TerminalEvents {
+    fn next_event(&mut self, timeout: Option<Duration>) -> crossterm::Result<Option<Event>> {
+        match timeout {
+            Some(timeout) if !event::poll(timeout)? => Ok(None),
+            _ => Ok(Some(event::read()?)),
+        }
+    }
+}
+
+impl Drop for TerminalEvents {
+    fn drop(&mut self) {
+        let disable_result = disable_raw_mode();
+        if disable_result.is_err() {
+            eprintln!("could not disable raw mode");
+        }
+    }
+}
+
+/// Events given up front, e.g. to drive the editor without a terminal. Keys
+/// of a sequence never time out while there are more events, and the editor
+/// stops when the events run out.
+#[derive(Debug, Default)]
+pub struct ScriptedEvents {
+    events: VecDeque<Event>,
+}
+
+impl ScriptedEvents {
+    /// Creates events from keys written like "ojLunch<Esc>tjj", see
+    /// keymap::parse_script
+    pub fn from_script(script: &str) -> Result<ScriptedEvents, KeymapError> {
+        let mut events = ScriptedEvents::default();
+        events.push_script(script)?;
+        Ok(events)
+    }
+
+    pub fn push(&mut self, event: Event) {
+        self.events.push_back(event);
+    }
+
+    pub fn push_keys(&mut self, keys: impl IntoIterator<Item = KeyEvent>) {
+        self.events.extend(keys.into_iter().map(Event::Key));
+    }
+
+    pub fn push_script(&mut self, script: &str) -> Result<(), KeymapError> {
+        self.push_keys(parse_script(script)?);
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+impl EventSource for ScriptedEvents {
+    fn next_event(&mut self, _timeout: Option<Duration>) -> crossterm::Result<Option<Event>> {
+        Ok(self.events.pop_front())
+    }
+
+    fn is_finished(&self) -> bool {
+        self.events.is_empty()
+    }
+}
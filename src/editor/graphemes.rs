@@ -0,0 +1,95 @@
+//! Text columns counted in grapheme clusters, i.e. what the user sees as one
+//! character, e.g. "ä" written as "a" and a combining diaeresis, or an emoji
+//! with a skin tone. Positions on screen count display columns instead, where
+//! CJK characters and most emoji take two.
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Number of grapheme clusters in text
+pub fn count(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+/// Byte offset of the grapheme at column col, or the length of text if col is
+/// past the end
+pub fn byte_offset(text: &str, col: usize) -> usize {
+    text.grapheme_indices(true)
+        .nth(col)
+        .map(|(offset, _)| offset)
+        .unwrap_or_else(|| text.len())
+}
+
+/// Columns the text takes on screen
+pub fn width(text: &str) -> usize {
+    text.graphemes(true).map(grapheme_width).sum()
+}
+
+/// Columns a single grapheme takes on screen. Terminals draw emoji sequences
+/// like "👨‍👩‍👧" as one wide character, not one per emoji in it.
+pub fn grapheme_width(grapheme: &str) -> usize {
+    grapheme.width().min(2)
+}
+
+/// Columns the graphemes before column col take on screen
+pub fn width_before(text: &str, col: usize) -> usize {
+    width(&text[..byte_offset(text, col)])
+}
+
+/// Column of the grapheme drawn at screen column x, counting from the start of
+/// text. A wide grapheme is hit by both of its screen columns. Past the end
+/// each screen column is one column.
+pub fn col_at(text: &str, x: usize) -> usize {
+    let mut left = 0;
+    for (col, grapheme) in text.graphemes(true).enumerate() {
+        let right = left + grapheme_width(grapheme);
+        if x < right {
+            return col;
+        }
+        left = right;
+    }
+    count(text) + (x - left)
+}
+
+/// Inserts s before column col. Returns the column after the inserted text,
+/// which may have joined the grapheme before it, e.g. a combining accent.
+pub fn insert(text: &mut String, col: usize, s: &str) -> usize {
+    let offset = byte_offset(text, col);
+    text.insert_str(offset, s);
+    count(&text[..offset + s.len()])
+}
+
+/// Removes the grapheme at column col. Returns false if there is none.
+pub fn remove(text: &mut String, col: usize) -> bool {
+    remove_range(text, col..col + 1)
+}
+
+/// Removes the graphemes at given columns. Returns false if there are none.
+pub fn remove_range(text: &mut String, cols: Range<usize>) -> bool {
+    let start = byte_offset(text, cols.start);
+    let end = byte_offset(text, cols.end);
+    text.replace_range(start..end, "");
+    start != end
+}
+
+#[test]
+fn grapheme_columns() {
+    // "ä" decomposed, a wide CJK character and a family emoji
+    let mut text = "a\u{308}漢👨‍👩‍👧!".to_owned();
+    assert_eq!(count(&text), 4);
+    assert_eq!(width(&text), 6);
+    assert_eq!(width_before(&text, 2), 3);
+    assert_eq!(
+        (0..8).map(|x| col_at(&text, x)).collect::<Vec<_>>(),
+        vec![0, 1, 1, 2, 2, 3, 4, 5]
+    );
+
+    assert!(remove(&mut text, 2));
+    assert_eq!(text, "a\u{308}漢!");
+    assert!(!remove(&mut text, 3));
+
+    // A combining accent joins the grapheme before it
+    assert_eq!(insert(&mut text, 3, "o"), 4);
+    assert_eq!(insert(&mut text, 4, "\u{308}"), 4);
+    assert_eq!(text, "a\u{308}漢!o\u{308}");
+}
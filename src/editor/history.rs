@@ -0,0 +1,115 @@
+use std::collections::VecDeque;
+
+use crate::schedule::Schedule;
+
+/// Maximum number of undo steps kept in memory
+pub const HISTORY_DEPTH: usize = 100;
+
+/// Undo and redo stacks of schedule snapshots.
+///
+/// Each snapshot is the state of the schedule before an edit. Consecutive
+/// edits can be coalesced into a single undo step, e.g. when typing text in
+/// insert mode.
+#[derive(Debug)]
+pub struct History {
+    undo: VecDeque<Schedule>,
+    redo: Vec<Schedule>,
+    depth: usize,
+    /// Set while edits are being coalesced into the latest undo step
+    coalescing: bool,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History::with_depth(HISTORY_DEPTH)
+    }
+}
+
+impl History {
+    pub fn with_depth(depth: usize) -> History {
+        History {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            depth,
+            coalescing: false,
+        }
+    }
+
+    /// Records the state of the schedule before an edit as a new undo step.
+    pub fn record(&mut self, before: Schedule) {
+        self.coalescing = false;
+        self.push(before);
+    }
+
+    /// Records the state of the schedule before an edit, unless a previous
+    /// edit has already started the current undo step.
+    pub fn record_coalesced(&mut self, before: Schedule) {
+        if !self.coalescing {
+            self.push(before);
+            self.coalescing = true;
+        }
+    }
+
+    /// Ends the current coalesced undo step
+    pub fn break_coalescing(&mut self) {
+        self.coalescing = false;
+    }
+
+    /// Returns the schedule before the latest edit, or None if there is
+    /// nothing to undo.
+    pub fn undo(&mut self, current: &Schedule) -> Option<Schedule> {
+        self.coalescing = false;
+        let prev = self.undo.pop_back()?;
+        self.redo.push(current.clone());
+        Some(prev)
+    }
+
+    /// Returns the schedule before the latest undo, or None if there is
+    /// nothing to redo.
+    pub fn redo(&mut self, current: &Schedule) -> Option<Schedule> {
+        self.coalescing = false;
+        let next = self.redo.pop()?;
+        self.undo.push_back(current.clone());
+        Some(next)
+    }
+
+    fn push(&mut self, before: Schedule) {
+        // A new edit invalidates the undone changes
+        self.redo.clear();
+
+        self.undo.push_back(before);
+        while self.undo.len() > self.depth {
+            self.undo.pop_front();
+        }
+    }
+}
+
+#[test]
+fn history_undo_redo() {
+    use crate::{dom::TimeBox, time::Time};
+
+    let schedule = |len| Schedule {
+        timeboxes: vec![TimeBox::default(); len],
+        wake_up: Time::hm(9, 0),
+        wake_up_tomorrow: Time::hm(9, 0),
+        sunrise: None,
+        sunset: None,
+    };
+
+    let mut history = History::with_depth(2);
+    history.record(schedule(0));
+    // Coalesced edits only store the first snapshot
+    history.record_coalesced(schedule(1));
+    history.record_coalesced(schedule(2));
+    history.break_coalescing();
+    history.record(schedule(3));
+
+    // Depth is capped, the oldest snapshot is dropped
+    assert_eq!(history.undo(&schedule(4)), Some(schedule(3)));
+    assert_eq!(history.undo(&schedule(3)), Some(schedule(1)));
+    assert_eq!(history.undo(&schedule(1)), None);
+
+    assert_eq!(history.redo(&schedule(1)), Some(schedule(3)));
+    assert_eq!(history.redo(&schedule(3)), Some(schedule(4)));
+    assert_eq!(history.redo(&schedule(4)), None);
+}
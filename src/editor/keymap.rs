@@ -0,0 +1,482 @@
+//! Key bindings in effect: the defaults in `keys::BINDINGS` with the user's
+//! overrides from the config file on top.
+//!
+//! ```toml
+//! [keys.global]
+//! "ctrl+w" = ":w"
+//!
+//! [keys.cursor]
+//! x = "cut-current-line"
+//! "g t" = "move-cursor top"
+//! "alt+o" = ["insert-time-box-below", "insert-mode"]
+//!
+//! [keys.time]
+//! "+" = "adjust-time +0:30"
+//! ```
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::{borrow::Cow, collections::BTreeMap, str::FromStr};
+use thiserror::Error;
+
+use super::{
+    command::{Binding, ColumnKind, Command, Dir, Filter, KeyTrie, MoveCursor},
+    Mode,
+};
+use crate::{keys::BINDINGS, time::Duration};
+
+/// Table of bindings of the config file: mode or "global" -> key -> command
+pub type KeyConfig = BTreeMap<String, BTreeMap<String, KeyCommand>>;
+
+/// A command name or a sequence of them
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum KeyCommand {
+    One(String),
+    Multi(Vec<String>),
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum KeymapError {
+    #[error("unknown mode '{0}'")]
+    UnknownMode(String),
+    #[error("invalid key '{0}'")]
+    InvalidKey(String),
+    #[error("unknown command '{command}' bound to '{key}'")]
+    UnknownCommand { key: String, command: String },
+    #[error("'{key}' is bound to '{command}' which is not a valid command: {reason}")]
+    InvalidCommand {
+        key: String,
+        command: String,
+        reason: String,
+    },
+    #[error("'{0}' is bound more than once in overlapping modes")]
+    Conflict(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    /// Searched in order, the first match wins
+    pub bindings: Vec<Binding>,
+    pub trie: KeyTrie,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::new(BINDINGS.to_vec())
+    }
+}
+
+impl Keymap {
+    pub fn new(bindings: Vec<Binding>) -> Keymap {
+        Keymap {
+            trie: KeyTrie::new(&bindings),
+            bindings,
+        }
+    }
+
+    /// Merges the user's bindings over the defaults. Every invalid binding is
+    /// reported, not only the first one.
+    pub fn with_overrides(config: &KeyConfig) -> Result<Keymap, Vec<KeymapError>> {
+        let mut overrides: Vec<(String, Binding)> = vec![];
+        let mut errors = vec![];
+
+        for (filter_name, keys) in config {
+            let filter = match parse_filter(filter_name) {
+                Ok(filter) => filter,
+                Err(e) => {
+                    errors.push(e);
+                    continue;
+                }
+            };
+
+            for (key_name, command) in keys {
+                let binding = parse_keys(key_name).and_then(|keys| {
+                    Ok(Binding {
+                        keys: Cow::Owned(keys),
+                        command: parse_key_command(key_name, command)?,
+                        filter: filter.clone(),
+                    })
+                });
+                match binding {
+                    Ok(binding) => {
+                        // The same key may be spelled differently, e.g. "G"
+                        // and "shift+g"
+                        let conflict = overrides.iter().any(|(_, other)| {
+                            other.keys == binding.keys && overlaps(&other.filter, &binding.filter)
+                        });
+                        if conflict {
+                            errors.push(KeymapError::Conflict(key_name.clone()));
+                        } else {
+                            overrides.push((key_name.clone(), binding));
+                        }
+                    }
+                    Err(e) => errors.push(e),
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        // Overrides come first so that they shadow the defaults
+        let mut bindings: Vec<Binding> = overrides.into_iter().map(|(_, b)| b).collect();
+        bindings.extend_from_slice(BINDINGS);
+        Ok(Keymap::new(bindings))
+    }
+}
+
+fn overlaps(a: &Filter, b: &Filter) -> bool {
+    match (a, b) {
+        (Filter::Global, _) | (_, Filter::Global) => true,
+        (Filter::Mode(m), other) | (other, Filter::Mode(m)) => other.match_mode(m),
+        (Filter::Modes(ms), other) => ms.iter().any(|m| other.match_mode(m)),
+    }
+}
+
+fn parse_filter(s: &str) -> Result<Filter, KeymapError> {
+    if s == "global" {
+        return Ok(Filter::Global);
+    }
+    Mode::from_str(s)
+        .map(Filter::Mode)
+        .map_err(|_| KeymapError::UnknownMode(s.to_owned()))
+}
+
+/// Parses space separated keys pressed one after another, e.g. "g g" or
+/// "ctrl+x ctrl+s".
+pub fn parse_keys(s: &str) -> Result<Vec<KeyEvent>, KeymapError> {
+    let keys = s
+        .split_whitespace()
+        .map(parse_key)
+        .collect::<Result<Vec<_>, _>>()?;
+    if keys.is_empty() {
+        return Err(KeymapError::InvalidKey(s.to_owned()));
+    }
+    Ok(keys)
+}
+
+/// Parses keys typed one after another written like in Vim, e.g.
+/// "ojLunch<Esc>tjj". Special keys and chords go in angle brackets: "<Esc>",
+/// "<CR>", "<BS>", "<C-r>", "<A-j>" and "<lt>" for "<".
+pub fn parse_script(s: &str) -> Result<Vec<KeyEvent>, KeymapError> {
+    let mut keys = vec![];
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '<' {
+            keys.push(parse_key(&c.to_string())?);
+            continue;
+        }
+
+        let rest = chars.as_str();
+        let end = rest
+            .find('>')
+            .ok_or_else(|| KeymapError::InvalidKey(format!("<{}", rest)))?;
+        let mut name = &rest[..end];
+        chars = rest[end + 1..].chars();
+
+        // Modifiers are single letters followed by a dash, e.g. "C-" in
+        // "<C-->"
+        let mut chord = String::new();
+        while name.len() > 2 && name.as_bytes()[1] == b'-' {
+            chord.push_str(&name[..1]);
+            chord.push('+');
+            name = &name[2..];
+        }
+        chord.push_str(match name.to_lowercase().as_str() {
+            "cr" => "enter",
+            "bs" => "backspace",
+            "lt" => "<",
+            _ => name,
+        });
+        keys.push(parse_key(&chord)?);
+    }
+    Ok(keys)
+}
+
+/// Parses a key chord like "x", "G", "ctrl+q", "alt+j" or "esc".
+fn parse_key(s: &str) -> Result<KeyEvent, KeymapError> {
+    let invalid = || KeymapError::InvalidKey(s.to_owned());
+
+    // Split modifiers from the key, "+" alone is a key
+    let mut parts: Vec<&str> = s.split('+').collect();
+    let key = match parts.pop() {
+        Some("") if s.ends_with('+') => {
+            parts.pop();
+            "+"
+        }
+        Some(key) => key,
+        None => return Err(invalid()),
+    };
+
+    let mut modifiers = KeyModifiers::NONE;
+    for part in parts {
+        modifiers |= match part.to_lowercase().as_str() {
+            "ctrl" | "c" => KeyModifiers::CONTROL,
+            "alt" | "a" | "m" => KeyModifiers::ALT,
+            "shift" | "s" => KeyModifiers::SHIFT,
+            _ => return Err(invalid()),
+        };
+    }
+
+    let mut chars = key.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => {
+            // Terminals report upper case letters with shift, e.g. "shift+g"
+            // and "G" are the same key
+            if c.is_ascii_uppercase() {
+                modifiers |= KeyModifiers::SHIFT;
+            }
+            if c.is_ascii_alphabetic() && modifiers.contains(KeyModifiers::SHIFT) {
+                KeyCode::Char(c.to_ascii_uppercase())
+            } else {
+                KeyCode::Char(c)
+            }
+        }
+        _ => match key.to_lowercase().as_str() {
+            "esc" | "escape" => KeyCode::Esc,
+            "enter" | "return" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "space" => KeyCode::Char(' '),
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            f if f.starts_with('f') => KeyCode::F(f[1..].parse::<u8>().map_err(|_| invalid())?),
+            _ => return Err(invalid()),
+        },
+    };
+
+    Ok(KeyEvent { code, modifiers })
+}
+
+fn parse_key_command(key: &str, command: &KeyCommand) -> Result<Command, KeymapError> {
+    match command {
+        KeyCommand::One(name) => parse_command(key, name),
+        KeyCommand::Multi(names) => Ok(Command::Multi(Cow::Owned(
+            names
+                .iter()
+                .map(|name| parse_command(key, name))
+                .collect::<Result<Vec<_>, _>>()?,
+        ))),
+    }
+}
+
+/// Parses a command name like "paste-below", "move-cursor up" or
+/// "adjust-time -0:15". Names starting with ':' are parsed like the command
+/// line, e.g. ":w".
+fn parse_command(key: &str, s: &str) -> Result<Command, KeymapError> {
+    let invalid = |reason: String| KeymapError::InvalidCommand {
+        key: key.to_owned(),
+        command: s.to_owned(),
+        reason,
+    };
+
+    if let Some(command_line) = s.strip_prefix(':') {
+        return Command::from_str(command_line).map_err(|e| invalid(e.to_string()));
+    }
+
+    let mut args = s.split_whitespace();
+    let name = args.next().unwrap_or_default();
+    let mut arg = |what: &str| {
+        args.next()
+            .ok_or_else(|| invalid(format!("missing {}", what)))
+    };
+
+    let cmd = match name {
+        "quit" => Command::Quit,
+        "insert-mode" => Command::InsertMode,
+        "cursor-mode" => Command::CursorMode,
+        "time-mode" => Command::TimeMode,
+        "visual-mode" => Command::VisualMode,
+        "insert-time-box-below" => Command::InsertTimeBoxBelow,
+        "insert-time-box-above" => Command::InsertTimeBoxAbove,
+        "toggle-cross-over" => Command::ToggleCrossOver,
+        "toggle-time-adjust-policy-fixed" => Command::ToggleTimeAdjustPolicyFixed,
+        "toggle-between-span-and-time" => Command::ToggleBetweenSpanAndTime,
+        "paste-above" => Command::PasteAbove,
+        "paste-below" => Command::PasteBelow,
+        "move-time-cursor" => Command::MoveTimeCursor,
+        "swap-time-sub-mode" => Command::SwapTimeSubMode,
+        "delete-time" => Command::DeleteTime,
+        "cut-current-line" => Command::CutCurrentLine,
+        "cut-lines" => Command::CutLines(match arg("direction")? {
+            "up" => Dir::Up,
+            "down" => Dir::Down,
+            other => return Err(invalid(format!("unknown direction '{}'", other))),
+        }),
+        "yank-current-line" => Command::YankCurrentLine,
+        "yank-lines" => Command::YankLines(match arg("direction")? {
+            "up" => Dir::Up,
+            "down" => Dir::Down,
+            other => return Err(invalid(format!("unknown direction '{}'", other))),
+        }),
+        "yank-all" => Command::YankAll(None),
+        "open-command-input" => Command::OpenCommandInput,
+        "save" => Command::Save,
+        "save-and-quit" => Command::SaveAndQuit,
+        "undo" => Command::Undo,
+        "redo" => Command::Redo,
+        "sort" => Command::Sort,
+        "search" => Command::Search,
+        "confirm-search" => Command::ConfirmSearch,
+        "cancel-search" => Command::CancelSearch,
+        "search-next" => Command::SearchNext,
+        "search-previous" => Command::SearchPrevious,
+        "jump-to-now" => Command::JumpToNow,
+        name @ "move-time-box" | name @ "move-activity" => Command::MoveTimeBox {
+            dir: match arg("direction")? {
+                "up" => Dir::Up,
+                "down" => Dir::Down,
+                other => return Err(invalid(format!("unknown direction '{}'", other))),
+            },
+            keep_times: name == "move-activity",
+        },
+        "catch-up" => Command::CatchUp,
+        "set-activity-kind" => Command::from_str(&format!("kind {}", arg("activity kind")?))
+            .map_err(|e| invalid(e.to_string()))?,
+        "move-cursor" => Command::MoveCursor(match arg("direction")? {
+            "up" => MoveCursor::Dir(Dir::Up),
+            "down" => MoveCursor::Dir(Dir::Down),
+            "left" => MoveCursor::Dir(Dir::Left),
+            "right" => MoveCursor::Dir(Dir::Right),
+            "top" => MoveCursor::Top,
+            "bottom" => MoveCursor::Bottom,
+            "page-up" => MoveCursor::PageUp,
+            "page-down" => MoveCursor::PageDown,
+            other => parse_motion(other)
+                .ok_or_else(|| invalid(format!("unknown direction '{}'", other)))?,
+        }),
+        "delete-to" => Command::DeleteTo(
+            parse_motion(arg("motion")?).ok_or_else(|| invalid("unknown motion".to_owned()))?,
+        ),
+        "go-to-column" => Command::GoToColumn(match arg("column")? {
+            "last" => ColumnKind::Last,
+            n => ColumnKind::Index(
+                n.parse()
+                    .map_err(|_| invalid(format!("invalid column '{}'", n)))?,
+            ),
+        }),
+        "adjust-time" => {
+            let minutes = Duration::from_str(arg("duration")?)
+                .map_err(invalid)?
+                .num_minutes();
+            Command::AdjustTime {
+                hours: (minutes / 60) as i8,
+                minutes: (minutes % 60) as i8,
+            }
+        }
+        _ => {
+            return Err(KeymapError::UnknownCommand {
+                key: key.to_owned(),
+                command: s.to_owned(),
+            })
+        }
+    };
+
+    match args.next() {
+        Some(extra) => Err(invalid(format!("unexpected '{}'", extra))),
+        None => Ok(cmd),
+    }
+}
+
+/// Parses a motion within text like "word-forward" or "line-start"
+fn parse_motion(s: &str) -> Option<MoveCursor> {
+    Some(match s {
+        "word-forward" => MoveCursor::WordForward,
+        "word-backward" => MoveCursor::WordBackward,
+        "word-end" => MoveCursor::WordEnd,
+        "line-start" => MoveCursor::LineStart,
+        "line-end" => MoveCursor::LineEnd,
+        "first-non-blank" => MoveCursor::FirstNonBlank,
+        _ => return None,
+    })
+}
+
+#[test]
+fn keymap_overrides() {
+    let config: KeyConfig = toml::from_str(
+        r#"
+        [cursor]
+        x = "cut-current-line"
+        "g t" = "move-cursor top"
+        j = ["move-cursor down", "move-cursor down"]
+        "ctrl+w" = ":w"
+
+        [time]
+        "+" = "adjust-time +1:30"
+        "#,
+    )
+    .unwrap();
+    let keymap = Keymap::with_overrides(&config).unwrap();
+    let find = |key: &str, mode: Mode| {
+        let keys = parse_keys(key).unwrap();
+        keymap
+            .bindings
+            .iter()
+            .find(|b| *b.keys == *keys && b.filter.match_mode(&mode))
+            .map(|b| b.command.clone())
+    };
+
+    assert_eq!(find("x", Mode::Cursor), Some(Command::CutCurrentLine));
+    assert_eq!(find("ctrl+w", Mode::Cursor), Some(Command::Save));
+    assert_eq!(
+        find("g t", Mode::Cursor),
+        Some(Command::MoveCursor(MoveCursor::Top))
+    );
+    assert_eq!(
+        find("+", Mode::Time),
+        Some(Command::AdjustTime {
+            hours: 1,
+            minutes: 30
+        })
+    );
+    // Override shadows the default
+    assert!(matches!(find("j", Mode::Cursor), Some(Command::Multi(_))));
+    // Defaults are kept
+    assert_eq!(find("i", Mode::Cursor), Some(Command::InsertMode));
+    assert_eq!(parse_key("G"), parse_key("shift+g"));
+
+    let config: KeyConfig = toml::from_str(
+        r#"
+        [global]
+        "ctrl+x" = "quit"
+
+        [cursor]
+        "c+x" = "save"
+        y = "yank"
+
+        [replace]
+        v = "quit"
+        "#,
+    )
+    .unwrap();
+    let errors = Keymap::with_overrides(&config).unwrap_err();
+    assert_eq!(errors.len(), 3);
+    assert!(errors.iter().any(|e| matches!(e, KeymapError::Conflict(_))));
+    assert!(errors.contains(&KeymapError::UnknownMode("replace".to_owned())));
+}
+
+#[test]
+fn script_keys() {
+    let key = |code, modifiers| KeyEvent { code, modifiers };
+    assert_eq!(
+        parse_script("oA<Esc><C-r><lt><CR>").unwrap(),
+        vec![
+            key(KeyCode::Char('o'), KeyModifiers::NONE),
+            key(KeyCode::Char('A'), KeyModifiers::SHIFT),
+            key(KeyCode::Esc, KeyModifiers::NONE),
+            key(KeyCode::Char('r'), KeyModifiers::CONTROL),
+            key(KeyCode::Char('<'), KeyModifiers::NONE),
+            key(KeyCode::Enter, KeyModifiers::NONE),
+        ]
+    );
+    assert!(parse_script("<Esc").is_err());
+    assert!(parse_script("<nope>").is_err());
+}
@@ -5,15 +5,25 @@ pub mod cursor;
 mod cursor_pos;
 mod edit_schedule;
 mod error;
+pub mod events;
+mod graphemes;
+mod history;
+pub mod keymap;
 mod mode;
+mod motion;
+mod registers;
 mod render;
+pub mod search;
 pub mod state;
 pub mod text_capture;
+mod viewport;
 
 pub use api::EditorLike;
 pub use error::Error;
+pub use events::{EventSource, ScriptedEvents, TerminalEvents};
+pub use keymap::Keymap;
 pub use mode::Mode;
-pub use render::Draw;
+pub use render::{Draw, Theme};
 pub use state::State;
 
 pub type Result<T> = std::result::Result<T, Error>;
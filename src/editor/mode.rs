@@ -1,3 +1,5 @@
+use std::str::FromStr;
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Mode {
     // Move cursor, use general commands
@@ -6,20 +8,30 @@ pub enum Mode {
     Insert,
     // Adjust time
     Time,
-    // Go to something (transient)
-    GoTo,
-    // Delete something (transient)
-    Delete,
+    // Type a search
+    Search,
+    // Select lines to act on together
+    Visual,
 }
 
 impl Mode {
-    pub fn is_transient(&self) -> bool {
-        match self {
-            Mode::Cursor => false,
-            Mode::Insert => false,
-            Mode::Time => false,
-            Mode::GoTo => true,
-            Mode::Delete => true,
+    /// Keys are typed as text instead of forming counts
+    pub fn types_text(&self) -> bool {
+        matches!(self, Mode::Insert | Mode::Search)
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cursor" => Ok(Mode::Cursor),
+            "insert" => Ok(Mode::Insert),
+            "time" => Ok(Mode::Time),
+            "search" => Ok(Mode::Search),
+            "visual" => Ok(Mode::Visual),
+            _ => Err(format!("unknown mode '{}'", s)),
         }
     }
 }
@@ -0,0 +1,164 @@
+//! Where word-wise and line-wise motions take the cursor. Columns are counted
+//! in graphemes, see `graphemes`.
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{command::MoveCursor, cursor::MappedPos, graphemes};
+use crate::schedule::Schedule;
+
+/// Words are runs of letters, digits and underscores, or runs of other
+/// non-blank characters, like in Vim
+#[derive(Clone, Copy, PartialEq)]
+enum Class {
+    Blank,
+    Word,
+    Punctuation,
+}
+
+fn classes(text: &str) -> Vec<Class> {
+    text.graphemes(true)
+        .map(|grapheme| match grapheme.chars().next() {
+            Some(c) if c.is_whitespace() => Class::Blank,
+            Some(c) if c.is_alphanumeric() || c == '_' => Class::Word,
+            _ => Class::Punctuation,
+        })
+        .collect()
+}
+
+/// Start of the next word after column col, None if there is none on the line
+pub fn next_word_start(text: &str, col: usize) -> Option<usize> {
+    let classes = classes(text);
+    let current = *classes.get(col)?;
+    let mut col = col;
+    while col < classes.len() && classes[col] == current {
+        col += 1;
+    }
+    while col < classes.len() && classes[col] == Class::Blank {
+        col += 1;
+    }
+    if col < classes.len() {
+        Some(col)
+    } else {
+        None
+    }
+}
+
+/// Start of the word before column col, or of the word col is in. None if
+/// there is none on the line.
+pub fn prev_word_start(text: &str, col: usize) -> Option<usize> {
+    let classes = classes(text);
+    let mut col = col.min(classes.len());
+    while col > 0 && classes[col - 1] == Class::Blank {
+        col -= 1;
+    }
+    if col == 0 {
+        return None;
+    }
+    let current = classes[col - 1];
+    while col > 0 && classes[col - 1] == current {
+        col -= 1;
+    }
+    Some(col)
+}
+
+/// End of the word after column col, or of the word col is in. None if there
+/// is none on the line.
+pub fn word_end(text: &str, col: usize) -> Option<usize> {
+    let classes = classes(text);
+    let mut col = col + 1;
+    while col < classes.len() && classes[col] == Class::Blank {
+        col += 1;
+    }
+    classes.get(col)?;
+    Some(end_of_run(&classes, col))
+}
+
+/// Last column of the run of the same class starting at col
+fn end_of_run(classes: &[Class], col: usize) -> usize {
+    let mut col = col;
+    while col + 1 < classes.len() && classes[col + 1] == classes[col] {
+        col += 1;
+    }
+    col
+}
+
+/// Column of the first non-blank character, or the end of the line
+pub fn first_non_blank(text: &str) -> usize {
+    classes(text)
+        .iter()
+        .position(|class| *class != Class::Blank)
+        .unwrap_or_else(|| graphemes::count(text))
+}
+
+/// Where a motion within the text takes the cursor from pos. Word motions
+/// continue on the next or the previous line. None for motions between lines.
+pub fn target(motion: &MoveCursor, pos: &MappedPos, schedule: &Schedule) -> Option<MappedPos> {
+    let text = |line: usize| schedule.timeboxes[line].activity.summary.as_str();
+    let on_line = |col: usize| Some(MappedPos::new(col, pos.line));
+    let last_line = schedule.timeboxes.len() - 1;
+
+    match motion {
+        MoveCursor::WordForward => match next_word_start(text(pos.line), pos.col) {
+            Some(col) => on_line(col),
+            None if pos.line < last_line => {
+                let line = pos.line + 1;
+                Some(MappedPos::new(first_non_blank(text(line)), line))
+            }
+            None => on_line(graphemes::count(text(pos.line))),
+        },
+        MoveCursor::WordBackward => match prev_word_start(text(pos.line), pos.col) {
+            Some(col) => on_line(col),
+            None if pos.line > 0 => {
+                let line = pos.line - 1;
+                let col = prev_word_start(text(line), graphemes::count(text(line))).unwrap_or(0);
+                Some(MappedPos::new(col, line))
+            }
+            None => on_line(0),
+        },
+        MoveCursor::WordEnd => match word_end(text(pos.line), pos.col) {
+            Some(col) => on_line(col),
+            None if pos.line < last_line => {
+                let line = pos.line + 1;
+                let classes = classes(text(line));
+                let start = first_non_blank(text(line));
+                let col = if start < classes.len() {
+                    end_of_run(&classes, start)
+                } else {
+                    start
+                };
+                Some(MappedPos::new(col, line))
+            }
+            None => None,
+        },
+        MoveCursor::LineStart => on_line(0),
+        MoveCursor::LineEnd => on_line(graphemes::count(text(pos.line)).saturating_sub(1)),
+        MoveCursor::FirstNonBlank => on_line(first_non_blank(text(pos.line))),
+        MoveCursor::Dir(_)
+        | MoveCursor::Top
+        | MoveCursor::Bottom
+        | MoveCursor::PageUp
+        | MoveCursor::PageDown => None,
+    }
+}
+
+#[test]
+fn word_motions() {
+    let text = "Walk  the dog, x-ray";
+    assert_eq!(next_word_start(text, 0), Some(6));
+    assert_eq!(next_word_start(text, 10), Some(13));
+    assert_eq!(next_word_start(text, 13), Some(15));
+    assert_eq!(next_word_start(text, 15), Some(16));
+    assert_eq!(next_word_start(text, 17), None);
+
+    assert_eq!(prev_word_start(text, 6), Some(0));
+    assert_eq!(prev_word_start(text, 12), Some(10));
+    assert_eq!(prev_word_start(text, 20), Some(17));
+    assert_eq!(prev_word_start(text, 0), None);
+
+    assert_eq!(word_end(text, 0), Some(3));
+    assert_eq!(word_end(text, 3), Some(8));
+    assert_eq!(word_end(text, 12), Some(13));
+    assert_eq!(word_end(text, 19), None);
+
+    assert_eq!(first_non_blank("  ä"), 2);
+    assert_eq!(first_non_blank("  "), 2);
+}
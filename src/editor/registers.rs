@@ -0,0 +1,90 @@
+//! Registers holding cut and yanked items, like in Vim. A register is chosen
+//! by typing '"' and its name before the command, e.g. "ayy.
+use std::collections::HashMap;
+
+use crate::dom::TimeBox;
+
+/// The register used when none is named. It also gets a copy of everything
+/// cut or yanked into the other registers.
+pub const UNNAMED: char = '"';
+
+/// The system clipboard. It is written to with an OSC 52 escape sequence,
+/// but can not be read from the terminal, so pasting from it pastes what was
+/// last put there.
+pub const CLIPBOARD: char = '+';
+
+#[derive(Debug, Default)]
+pub struct Registers {
+    registers: HashMap<char, Vec<TimeBox>>,
+}
+
+impl Registers {
+    /// True for the names a register can have: letters, upper case ones
+    /// appending to the lower case register, and the clipboard as '+' or '*'
+    pub fn is_valid(name: char) -> bool {
+        name == UNNAMED || name == CLIPBOARD || name == '*' || name.is_ascii_alphabetic()
+    }
+
+    /// Items in a register, the unnamed one if None
+    pub fn get(&self, name: Option<char>) -> &[TimeBox] {
+        self.registers
+            .get(&normalize(name))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Puts items into a register, or appends them for an upper case name.
+    /// The unnamed register gets the register's new contents as well.
+    pub fn set(&mut self, name: Option<char>, items: Vec<TimeBox>) {
+        let appends = matches!(name, Some(c) if c.is_ascii_uppercase());
+        let name = normalize(name);
+        let register = self.registers.entry(name).or_default();
+        if appends {
+            register.extend(items);
+        } else {
+            *register = items;
+        }
+
+        if name != UNNAMED {
+            let copy = register.clone();
+            self.registers.insert(UNNAMED, copy);
+        }
+    }
+}
+
+/// Case and the alias of the clipboard do not matter when reading
+fn normalize(name: Option<char>) -> char {
+    match name {
+        None => UNNAMED,
+        Some('*') => CLIPBOARD,
+        Some(name) => name.to_ascii_lowercase(),
+    }
+}
+
+#[test]
+fn named_and_appending_registers() {
+    let item = |summary: &str| {
+        let mut time_box = TimeBox::default();
+        time_box.activity.summary = summary.to_owned();
+        time_box
+    };
+    let summaries = |items: &[TimeBox]| -> Vec<String> {
+        items
+            .iter()
+            .map(|time_box| time_box.activity.summary.clone())
+            .collect()
+    };
+
+    let mut registers = Registers::default();
+    registers.set(None, vec![item("Work")]);
+    registers.set(Some('a'), vec![item("Lunch")]);
+    registers.set(Some('A'), vec![item("Walk")]);
+
+    assert_eq!(summaries(registers.get(Some('a'))), vec!["Lunch", "Walk"]);
+    assert_eq!(summaries(registers.get(None)), vec!["Lunch", "Walk"]);
+    assert!(registers.get(Some('b')).is_empty());
+
+    registers.set(Some('*'), vec![item("Coffee")]);
+    assert_eq!(summaries(registers.get(Some(CLIPBOARD))), vec!["Coffee"]);
+    assert!(!Registers::is_valid('1'));
+}
@@ -1,40 +1,65 @@
+mod buffer;
+mod compositor;
 mod schedule;
 mod status_bar;
+mod theme;
 
 use crossterm::{cursor, QueueableCommand};
-use std::io::{Stdout, Write};
+use std::io::Write;
+use unicode_segmentation::UnicodeSegmentation;
 
-use super::Result;
+use super::{graphemes, Result};
+
+pub use buffer::Buffer;
+pub use compositor::Compositor;
+pub use theme::Theme;
 
 // TODO: merge to below
 pub trait Render {
-    fn render(&self, stdout: &mut Stdout) -> Result<()>;
+    /// Draws into the buffer, starting from row y
+    fn render(&self, buf: &mut Buffer, y: u16, theme: &Theme) -> Result<()>;
+}
+
+/// Cuts text wider than width on screen, marking the cut with an ellipsis.
+pub fn truncate(text: &str, width: usize) -> String {
+    if graphemes::width(text) <= width {
+        return text.to_owned();
+    }
+    let mut cut = String::new();
+    let mut cut_width = 0;
+    for grapheme in text.graphemes(true) {
+        cut_width += graphemes::grapheme_width(grapheme);
+        if cut_width >= width {
+            break;
+        }
+        cut.push_str(grapheme);
+    }
+    if width > 0 {
+        cut.push('…');
+    }
+    cut
 }
 
 // TODO: move/merge to render (name: draw > render)
 pub trait Draw {
-    fn draw_at(&self, x: u16, y: u16, mut stdout: Stdout) -> crossterm::Result<()> {
-        // Store terminal cursor position to allow moving it temporarily for draw
-        let cursor_origin = cursor::position()?;
-
+    /// Draws at given terminal position. The caller places the terminal
+    /// cursor afterwards.
+    fn draw_at(&self, x: u16, y: u16, out: &mut dyn Write) -> crossterm::Result<()> {
         // Move terminal cursor to draw location
-        stdout.queue(cursor::MoveTo(x, y))?;
-
-        self.draw(&mut stdout)?;
+        out.queue(cursor::MoveTo(x, y))?;
 
-        // Move terminal cursor back to where it started
-        stdout.queue(cursor::MoveTo(cursor_origin.0, cursor_origin.1))?;
+        self.draw(out)?;
 
-        stdout.flush()?;
+        out.flush()?;
         Ok(())
     }
 
-    fn draw(&self, stdout: &mut Stdout) -> crossterm::Result<()>;
+    fn draw(&self, out: &mut dyn Write) -> crossterm::Result<()>;
 }
 
-impl<'a> Draw for &'a str {
-    fn draw(&self, stdout: &mut Stdout) -> crossterm::Result<()> {
-        stdout.write_all(self.as_bytes())?;
+impl Draw for &str {
+    fn draw(&self, out: &mut dyn Write) -> crossterm::Result<()> {
+        out.write_all(self.as_bytes())?;
         Ok(())
     }
 }
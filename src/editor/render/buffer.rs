@@ -0,0 +1,145 @@
+use crossterm::style::ContentStyle;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::editor::graphemes;
+
+/// One character on screen with its style. A wide character is followed by a
+/// cell with an empty symbol that it covers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cell {
+    pub symbol: String,
+    pub style: ContentStyle,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell {
+            symbol: " ".to_owned(),
+            style: ContentStyle::default(),
+        }
+    }
+}
+
+impl Cell {
+    /// Covered by the wide character on its left
+    pub fn is_continuation(&self) -> bool {
+        self.symbol.is_empty()
+    }
+}
+
+/// An in-memory screen that is rendered into and then shown on the terminal
+/// by the compositor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Buffer {
+    width: u16,
+    height: u16,
+    cells: Vec<Cell>,
+}
+
+impl Buffer {
+    /// A blank buffer
+    pub fn new(width: u16, height: u16) -> Buffer {
+        Buffer {
+            width,
+            height,
+            cells: vec![Cell::default(); width as usize * height as usize],
+        }
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    pub fn get(&self, x: u16, y: u16) -> &Cell {
+        &self.cells[self.index(x, y)]
+    }
+
+    /// Writes text on a row starting from column x. Text outside the buffer is
+    /// cut off. Returns the column after the text.
+    pub fn set_str(&mut self, x: u16, y: u16, text: &str, style: ContentStyle) -> u16 {
+        if y >= self.height {
+            return x;
+        }
+
+        let mut x = x;
+        for symbol in text.graphemes(true) {
+            let width = graphemes::grapheme_width(symbol) as u16;
+            if width == 0 {
+                continue;
+            }
+            // A wide character is not split at the edge
+            if x + width > self.width {
+                break;
+            }
+
+            self.set_cell(x, y, symbol.to_owned(), style);
+            for covered in x + 1..x + width {
+                self.set_cell(covered, y, String::new(), style);
+            }
+            x += width;
+        }
+        x
+    }
+
+    /// Sets a cell, blanking what remains of a wide character it overwrites
+    fn set_cell(&mut self, x: u16, y: u16, symbol: String, style: ContentStyle) {
+        let idx = self.index(x, y);
+        if self.cells[idx].is_continuation() && !symbol.is_empty() && x > 0 {
+            self.cells[idx - 1].symbol = " ".to_owned();
+        }
+        if x + 1 < self.width && self.cells[idx + 1].is_continuation() {
+            self.cells[idx + 1].symbol = " ".to_owned();
+        }
+        self.cells[idx] = Cell { symbol, style };
+    }
+
+    /// Colors width cells from column x, keeping their text and attributes.
+    /// Colors not set in style are kept too.
+    pub fn set_colors(&mut self, x: u16, y: u16, width: u16, style: ContentStyle) {
+        if y >= self.height {
+            return;
+        }
+        for x in x..x.saturating_add(width).min(self.width) {
+            let idx = self.index(x, y);
+            let cell = &mut self.cells[idx];
+            if style.foreground_color.is_some() {
+                cell.style.foreground_color = style.foreground_color;
+            }
+            if style.background_color.is_some() {
+                cell.style.background_color = style.background_color;
+            }
+        }
+    }
+
+    /// Makes room for a blank row at y by moving the rows from y to end, not
+    /// inclusive, a row down. The last of them is pushed out.
+    pub fn insert_row(&mut self, y: u16, end: u16) {
+        let end = end.min(self.height);
+        if y >= end {
+            return;
+        }
+        let width = self.width as usize;
+        let (start, end) = (self.index(0, y), self.index(0, end));
+        self.cells[start..end].rotate_right(width);
+        for cell in &mut self.cells[start..start + width] {
+            *cell = Cell::default();
+        }
+    }
+
+    /// The text on a row without trailing whitespace, e.g. for tests
+    pub fn row_text(&self, y: u16) -> String {
+        (0..self.width)
+            .map(|x| self.get(x, y).symbol.as_str())
+            .collect::<String>()
+            .trim_end()
+            .to_owned()
+    }
+
+    fn index(&self, x: u16, y: u16) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+}
@@ -0,0 +1,137 @@
+use crossterm::{
+    cursor,
+    style::{self, ContentStyle},
+    terminal, QueueableCommand,
+};
+use std::io::Write;
+
+use super::Buffer;
+use crate::editor::graphemes;
+
+/// Shows rendered frames on the terminal, writing only the cells that changed
+/// since the previous frame.
+#[derive(Debug, Default)]
+pub struct Compositor {
+    previous: Option<Buffer>,
+}
+
+/// Consecutive changed cells on a row sharing a style
+#[derive(Debug, PartialEq)]
+pub struct Run {
+    pub x: u16,
+    pub y: u16,
+    pub text: String,
+    pub style: ContentStyle,
+}
+
+impl Compositor {
+    /// Forgets the previous frame, so that the next one is drawn completely,
+    /// e.g. after something else has drawn on the terminal
+    pub fn invalidate(&mut self) {
+        self.previous = None;
+    }
+
+    /// The frame drawn last
+    pub fn frame(&self) -> Option<&Buffer> {
+        self.previous.as_ref()
+    }
+
+    pub fn present<W: Write>(&mut self, frame: Buffer, out: &mut W) -> crate::editor::Result<()> {
+        let runs = match &self.previous {
+            Some(previous)
+                if previous.width() == frame.width() && previous.height() == frame.height() =>
+            {
+                diff(previous, &frame)
+            }
+            // Start from a blank screen
+            _ => {
+                out.queue(terminal::Clear(terminal::ClearType::All))?;
+                diff(&Buffer::new(frame.width(), frame.height()), &frame)
+            }
+        };
+
+        for run in runs {
+            out.queue(cursor::MoveTo(run.x, run.y))?
+                .queue(style::PrintStyledContent(run.style.apply(run.text)))?;
+        }
+        out.flush()?;
+
+        self.previous = Some(frame);
+        Ok(())
+    }
+}
+
+/// Finds the cells that differ between two frames of the same size
+pub fn diff(previous: &Buffer, next: &Buffer) -> Vec<Run> {
+    let mut runs: Vec<Run> = vec![];
+
+    for y in 0..next.height() {
+        for x in 0..next.width() {
+            let cell = next.get(x, y);
+            // Drawn together with the wide character on its left
+            if previous.get(x, y) == cell || cell.is_continuation() {
+                continue;
+            }
+
+            // Continue the run of the previous cell on this row if possible
+            match runs.last_mut() {
+                Some(run)
+                    if run.y == y
+                        && run.x + graphemes::width(&run.text) as u16 == x
+                        && run.style == cell.style =>
+                {
+                    run.text.push_str(&cell.symbol)
+                }
+                _ => runs.push(Run {
+                    x,
+                    y,
+                    text: cell.symbol.clone(),
+                    style: cell.style,
+                }),
+            }
+        }
+    }
+
+    runs
+}
+
+#[test]
+fn compositor_diffs_frames() {
+    let mut previous = Buffer::new(10, 2);
+    previous.set_str(0, 0, "09:00 Work", ContentStyle::default());
+    previous.set_str(0, 1, "12:00 Lunch", ContentStyle::default());
+
+    let mut next = previous.clone();
+    next.set_str(0, 0, "09:30", ContentStyle::default());
+    let bold = ContentStyle::new().attribute(style::Attribute::Bold);
+    next.set_str(6, 1, "Lunc", bold);
+
+    assert_eq!(
+        diff(&previous, &next),
+        vec![
+            Run {
+                x: 3,
+                y: 0,
+                text: "3".to_owned(),
+                style: ContentStyle::default()
+            },
+            Run {
+                x: 6,
+                y: 1,
+                text: "Lunc".to_owned(),
+                style: bold
+            },
+        ]
+    );
+    assert!(diff(&next, &next).is_empty());
+    assert_eq!(next.row_text(1), "12:00 Lunc");
+
+    // Only the changes are written
+    let mut compositor = Compositor::default();
+    let mut out = vec![];
+    compositor.present(previous, &mut out).unwrap();
+    out.clear();
+    compositor.present(next, &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains('3') && !out.contains("Work"));
+}
@@ -1,34 +1,44 @@
-use super::Render;
+use super::{truncate, Buffer, Render, Theme};
 use crate::{
+    diagnostics::{Diagnostic, Problem},
     dom::{timebox::AdjustPolicy, TimeBox, TimeSlotKind},
+    editor::{graphemes, search::Search},
     schedule::Schedule,
+    time::Time,
 };
 use chrono::NaiveTime;
-use crossterm::{
-    cursor,
-    style::{self, style},
-    QueueableCommand,
-};
+use crossterm::style::{self, ContentStyle};
 use std::{
     collections::HashMap,
-    io::{Stdout, Write},
+    ops::{Range, RangeInclusive},
 };
 use strfmt::strfmt;
 
 impl Render for Schedule {
-    fn render(&self, stdout: &mut Stdout) -> crate::editor::Result<()> {
+    fn render(&self, buf: &mut Buffer, y: u16, theme: &Theme) -> crate::editor::Result<()> {
+        self.render_lines(buf, y, theme, 0..self.timeboxes.len())
+    }
+}
+
+impl Schedule {
+    /// Renders only given lines from row y onwards, e.g. the ones visible when
+    /// scrolled. Lines before them still determine the colors.
+    pub fn render_lines(
+        &self,
+        buf: &mut Buffer,
+        y: u16,
+        theme: &Theme,
+        lines: Range<usize>,
+    ) -> crate::editor::Result<()> {
         let mut circ_sector = if self.sunrise.is_some() && self.sunset.is_some() {
             CircadianSector::PreDawn
         } else {
             CircadianSector::Day
         };
-        stdout.queue(style::SetForegroundColor(circ_sector.color()))?;
-
-        for time_box in self.timeboxes.iter() {
-            if self.sunrise.is_some() && self.sunset.is_some() {
-                let sunrise = self.sunrise.expect("logic error");
-                let sunset = self.sunset.expect("logic error");
 
+        let mut row = y;
+        for (idx, time_box) in self.timeboxes.iter().enumerate() {
+            if let (Some(sunrise), Some(sunset)) = (self.sunrise, self.sunset) {
                 if let Some(time) = &time_box.time {
                     let time = match time {
                         TimeSlotKind::Time(t) => t,
@@ -43,11 +53,14 @@ impl Render for Schedule {
                     };
                     if next_color {
                         circ_sector = circ_sector.next();
-                        stdout.queue(style::SetForegroundColor(circ_sector.color()))?;
                     }
                 }
             }
 
+            if !lines.contains(&idx) {
+                continue;
+            }
+
             let t_str = match &time_box.time {
                 Some(t) => format!("{}", t),
                 None => "     ".to_owned(),
@@ -57,57 +70,144 @@ impl Render for Schedule {
             let mut vars = HashMap::new();
             vars.insert("time".to_owned(), t_str);
             vars.insert("activity".to_owned(), format!("{}", time_box.activity));
-            let content = strfmt(&fmt, &vars)?;
+            let content = truncate(&strfmt(&fmt, &vars)?, buf.width() as usize);
 
-            let (set_styles, unset_styles): (Vec<_>, Vec<_>) =
-                time_box.resolve_styles().into_iter().unzip();
+            let style = time_box.resolve_styles().into_iter().fold(
+                ContentStyle::new().foreground(circ_sector.color(theme)),
+                |style, attr| style.attribute(attr),
+            );
+            buf.set_str(0, row, &content, style);
+            row += 1;
+        }
+
+        Ok(())
+    }
 
-            for set in set_styles {
-                stdout.queue(style::SetAttribute(set))?;
+    /// Highlights the item at the current time on given lines, drawn from row
+    /// y onwards like in `render_lines`
+    pub fn render_now(
+        &self,
+        buf: &mut Buffer,
+        y: u16,
+        theme: &Theme,
+        lines: Range<usize>,
+        now: &Time,
+    ) {
+        let current = self.line_at(now);
+        for (row, line) in (y..).zip(lines) {
+            if current == Some(line) {
+                let style = ContentStyle::new().background(theme.now);
+                buf.set_colors(0, row, buf.width(), style);
             }
+        }
+    }
 
-            let styled = style(content);
-            stdout
-                .queue(style::PrintStyledContent(styled))?
-                .queue(cursor::MoveToNextLine(1))?;
+    /// Draws the now line on a row of its own, e.g. "14:37 ─────"
+    pub fn render_now_line(&self, buf: &mut Buffer, row: u16, theme: &Theme, now: &Time) {
+        let rule = "─".repeat(buf.width() as usize);
+        let style = ContentStyle::new().foreground(theme.now_line);
+        buf.set_str(0, row, &format!("{} {}", now, rule), style);
+    }
 
-            for unset in unset_styles {
-                stdout.queue(style::SetAttribute(unset))?;
+    /// Colors the times of items with problems on given lines, drawn from row
+    /// y onwards like in `render_lines`
+    pub fn render_diagnostics(
+        &self,
+        buf: &mut Buffer,
+        y: u16,
+        theme: &Theme,
+        lines: Range<usize>,
+        diagnostics: &[Diagnostic],
+    ) {
+        let time_col_width = self.time_col_width() as u16;
+        for diagnostic in diagnostics {
+            if !lines.contains(&diagnostic.line) {
+                continue;
             }
+            let color = match diagnostic.problem {
+                Problem::Gap(_) => theme.gap,
+                Problem::Overlap { .. } | Problem::OutOfOrder { .. } => theme.conflict,
+            };
+            let row = y + (diagnostic.line - lines.start) as u16;
+            buf.set_colors(
+                0,
+                row,
+                time_col_width,
+                ContentStyle::new().foreground(color),
+            );
         }
-        stdout.queue(style::SetForegroundColor(style::Color::Reset))?;
-        stdout.flush()?;
+    }
 
-        Ok(())
+    /// Highlights the selected lines of visual mode among given lines, drawn
+    /// from row y onwards like in `render_lines`
+    pub fn render_selection(
+        &self,
+        buf: &mut Buffer,
+        y: u16,
+        theme: &Theme,
+        lines: Range<usize>,
+        selection: RangeInclusive<usize>,
+    ) {
+        let style = ContentStyle::new().background(theme.selection);
+        for (row, line) in (y..).zip(lines) {
+            if selection.contains(&line) {
+                buf.set_colors(0, row, buf.width(), style);
+            }
+        }
+    }
+
+    /// Highlights the matches of a search on given lines, drawn from row y
+    /// onwards like in `render_lines`
+    pub fn render_matches(
+        &self,
+        buf: &mut Buffer,
+        y: u16,
+        theme: &Theme,
+        lines: Range<usize>,
+        search: &Search,
+    ) {
+        let style = ContentStyle::new()
+            .foreground(style::Color::Black)
+            .background(theme.search);
+        let time_col_width = self.time_col_width();
+        let time_line = search.time_line(self);
+
+        for (row, line) in (y..).zip(lines) {
+            if time_line == Some(line) {
+                buf.set_colors(0, row, time_col_width as u16, style);
+            }
+            let summary = &self.timeboxes[line].activity.summary;
+            for cols in search.text_matches(summary) {
+                let x = time_col_width + 1 + graphemes::width_before(summary, cols.start);
+                let width = graphemes::width_before(summary, cols.end)
+                    - graphemes::width_before(summary, cols.start);
+                buf.set_colors(x as u16, row, width as u16, style);
+            }
+        }
     }
-}
 
-impl Schedule {
     pub fn time_col_width(&self) -> usize {
         self.timeboxes
             .iter()
             .filter_map(|x| x.time.as_ref())
-            .map(|x| format!("{}", x).len())
+            .map(|x| graphemes::width(&x.to_string()))
             .max_by(|x, y| x.cmp(y))
             .unwrap_or(0)
     }
 }
 
 impl TimeBox {
-    fn resolve_styles(&self) -> Vec<(style::Attribute, style::Attribute)> {
+    fn resolve_styles(&self) -> Vec<style::Attribute> {
         let mut styles = vec![];
 
         // Cross out done items
         if self.done {
-            styles.push((
-                style::Attribute::CrossedOut,
-                style::Attribute::NotCrossedOut,
-            ));
+            styles.push(style::Attribute::CrossedOut);
         }
 
         // Cursive fixed items
         if self.adjust_policy == AdjustPolicy::Fixed {
-            styles.push((style::Attribute::Bold, style::Attribute::NormalIntensity));
+            styles.push(style::Attribute::Bold);
         }
 
         styles
@@ -131,24 +231,88 @@ impl CircadianSector {
             CircadianSector::Night => CircadianSector::PreDawn,
         }
     }
-    fn color(&self) -> style::Color {
+    fn color(&self, theme: &Theme) -> style::Color {
         match self {
-            CircadianSector::PreDawn => style::Color::Rgb {
-                r: 235,
-                g: 180,
-                b: 180,
-            },
-            CircadianSector::Day => style::Color::Reset,
-            CircadianSector::Dusk => style::Color::Rgb {
-                r: 215,
-                g: 180,
-                b: 220,
-            },
-            CircadianSector::Night => style::Color::Rgb {
-                r: 100,
-                g: 100,
-                b: 255,
-            },
+            CircadianSector::PreDawn => theme.pre_dawn,
+            CircadianSector::Day => theme.day,
+            CircadianSector::Dusk => theme.dusk,
+            CircadianSector::Night => theme.night,
         }
     }
 }
+
+#[test]
+fn schedule_renders_into_buffer() {
+    use std::str::FromStr;
+
+    let schedule = Schedule::from_str(
+        "- [ ] 09:00 Work\n- [x] ! 12:00--13:00 meal: Lunch\n- [ ] A very long summary\n",
+    )
+    .unwrap();
+    let mut buf = Buffer::new(30, 4);
+    schedule
+        .render_lines(&mut buf, 1, &Theme::default(), 1..3)
+        .unwrap();
+
+    assert_eq!(buf.row_text(0), "");
+    assert_eq!(buf.row_text(1), "12:00--13:00 (1h) Lunch");
+    assert_eq!(buf.row_text(2), "                  A very long…");
+    assert!(buf.get(18, 1).style.attributes.has(style::Attribute::Bold));
+}
+
+#[test]
+fn now_is_highlighted() {
+    use std::str::FromStr;
+
+    let schedule =
+        Schedule::from_str("- [ ] 09:00 Work\n- [ ] 12:00--13:00 Lunch\n- [ ] 14:00 Walk\n")
+            .unwrap();
+    let theme = Theme::default();
+    let highlighted =
+        |buf: &Buffer, y: u16| buf.get(0, y).style.background_color == Some(theme.now);
+
+    let mut buf = Buffer::new(30, 3);
+    schedule.render_now(&mut buf, 0, &theme, 0..3, &Time::hm(12, 30));
+    assert!(highlighted(&buf, 1));
+    assert!(!highlighted(&buf, 0));
+    assert_eq!(schedule.now_separator(&Time::hm(12, 30)), Some(2));
+
+    // Between lunch and the walk nothing is highlighted
+    let mut buf = Buffer::new(30, 3);
+    schedule.render_now(&mut buf, 0, &theme, 0..3, &Time::hm(13, 30));
+    assert!((0..3).all(|y| !highlighted(&buf, y)));
+    assert_eq!(schedule.now_separator(&Time::hm(13, 30)), Some(2));
+
+    // The now line makes room for itself
+    let mut buf = Buffer::new(20, 4);
+    schedule.render_lines(&mut buf, 0, &theme, 0..3).unwrap();
+    buf.insert_row(2, 4);
+    schedule.render_now_line(&mut buf, 2, &theme, &Time::hm(13, 30));
+    assert_eq!(buf.row_text(1), "12:00--13:00 (1h) L…");
+    assert_eq!(buf.row_text(2), "13:30 ──────────────");
+    assert!(buf.row_text(3).starts_with("14:00"));
+
+    // Awake before the first item
+    let schedule =
+        Schedule::from_str("<!-- wake-up: 07:00 -->\n- [ ] Coffee\n- [ ] 09:00 Work\n").unwrap();
+    assert_eq!(schedule.now_separator(&Time::hm(8, 0)), Some(1));
+}
+
+#[test]
+fn selection_is_highlighted() {
+    use std::str::FromStr;
+
+    let schedule = Schedule::from_str(
+        "- [ ] 09:00 Work\n- [ ] Mail\n- [ ] 12:00 Lunch\n- [ ] Call\n- [ ] 14:00 Walk\n",
+    )
+    .unwrap();
+    let theme = Theme::default();
+
+    // Scrolled down a line, with the selection going past the screen
+    let mut buf = Buffer::new(30, 3);
+    schedule.render_selection(&mut buf, 0, &theme, 1..4, 2..=4);
+    let selected: Vec<bool> = (0..3)
+        .map(|y| buf.get(29, y).style.background_color == Some(theme.selection))
+        .collect();
+    assert_eq!(selected, vec![false, true, true]);
+}
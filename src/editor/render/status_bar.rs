@@ -1,21 +1,22 @@
-use std::{borrow::Borrow, cell::RefCell, io::Write};
+use std::{borrow::Borrow, cell::RefCell};
 
-use crossterm::{
-    cursor,
-    style::{self, style},
-    QueueableCommand,
-};
+use crossterm::style::ContentStyle;
 
-use crate::editor::{
-    error::ResourceOwnershipError,
-    state::{StatusBar, TimeMode},
-    Mode,
+use crate::{
+    diagnostics::{self, Diagnostic},
+    editor::{
+        command::{display_keys, PendingKeys},
+        error::ResourceOwnershipError,
+        graphemes,
+        state::{StatusBar, TimeMode},
+        Mode,
+    },
 };
 
-use super::Render;
+use super::{truncate, Buffer, Render, Theme};
 
 impl Render for StatusBar {
-    fn render(&self, stdout: &mut std::io::Stdout) -> crate::editor::Result<()> {
+    fn render(&self, buf: &mut Buffer, y: u16, theme: &Theme) -> crate::editor::Result<()> {
         let rc_mode = self
             .mode
             .upgrade()
@@ -23,30 +24,82 @@ impl Render for StatusBar {
         let cell_mode: &RefCell<Mode> = rc_mode.borrow();
         let mode: &Mode = &cell_mode.borrow();
         let mode_str = match mode {
-            Mode::Cursor => "",
+            Mode::Cursor | Mode::Search => "",
             Mode::Insert => "-- INSERT --",
+            Mode::Visual => "-- VISUAL LINE --",
             Mode::Time => {
                 let time_mode = self.time_mode.upgrade().ok_or_else(|| {
                     ResourceOwnershipError("'time_mode' has been dropped".to_string())
                 })?;
-                let time_mode: &RefCell<TimeMode> = &time_mode.borrow();
+                let time_mode: &RefCell<TimeMode> = time_mode.borrow();
                 let time_mode: &TimeMode = &time_mode.borrow();
                 match time_mode {
                     TimeMode::Relative => "-- ADJUST TIME (relative) --",
                     TimeMode::Absolute => "-- ADJUST TIME (absolute) --",
                 }
             }
-            Mode::GoTo => "goto +",
-            Mode::Delete => "delete +",
         };
 
-        let content = mode_str.to_string();
+        let rc_message = self
+            .message
+            .upgrade()
+            .ok_or_else(|| ResourceOwnershipError("'message' has been dropped".to_string()))?;
+        let cell_message: &RefCell<Option<String>> = rc_message.borrow();
+        let rc_prompt = self
+            .prompt
+            .upgrade()
+            .ok_or_else(|| ResourceOwnershipError("'prompt' has been dropped".to_string()))?;
+        let cell_prompt: &RefCell<Option<String>> = rc_prompt.borrow();
+        let content = match (&*cell_prompt.borrow(), &*cell_message.borrow()) {
+            (Some(prompt), _) => prompt.clone(),
+            (None, Some(message)) if mode_str.is_empty() => message.clone(),
+            (None, Some(message)) => format!("{} {}", mode_str, message),
+            (None, None) => mode_str.to_string(),
+        };
+
+        // Keys of an unfinished sequence, e.g. "3 d"
+        let rc_pending = self
+            .pending
+            .upgrade()
+            .ok_or_else(|| ResourceOwnershipError("'pending' has been dropped".to_string()))?;
+        let cell_pending: &RefCell<PendingKeys> = rc_pending.borrow();
+        let pending = cell_pending.borrow();
+        let content = if pending.is_empty() {
+            content
+        } else {
+            format!("{} {}", content, display_keys(&pending))
+        };
+
+        // Writing the last column of the bottom row could scroll the whole
+        // screen
+        let width = buf.width().saturating_sub(1) as usize;
+
+        // Problems in the schedule are summed up on the right
+        let rc_diagnostics = self
+            .diagnostics
+            .upgrade()
+            .ok_or_else(|| ResourceOwnershipError("'diagnostics' has been dropped".to_string()))?;
+        let cell_diagnostics: &RefCell<Vec<Diagnostic>> = rc_diagnostics.borrow();
+        let summary = diagnostics::summary(&cell_diagnostics.borrow());
+        let summary_width = graphemes::width(&summary);
+        let width = if summary.is_empty() || summary_width + 1 > width {
+            width
+        } else {
+            buf.set_str(
+                (width - summary_width) as u16,
+                y,
+                &summary,
+                ContentStyle::new().foreground(theme.conflict),
+            );
+            width - summary_width - 1
+        };
 
-        let styled = style(&content);
-        stdout
-            .queue(style::PrintStyledContent(styled))?
-            .queue(cursor::MoveToNextLine(1))?;
-        stdout.flush()?;
+        buf.set_str(
+            0,
+            y,
+            &truncate(content.trim_start(), width),
+            ContentStyle::default(),
+        );
 
         Ok(())
     }
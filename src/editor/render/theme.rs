@@ -0,0 +1,139 @@
+use crossterm::style::Color;
+use serde::{Deserialize, Deserializer};
+use std::convert::TryFrom;
+
+/// Colors used by the renderer. Configurable by the user.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Theme {
+    /// Items before sunrise
+    #[serde(deserialize_with = "color")]
+    pub pre_dawn: Color,
+    /// Items between sunrise and sunset
+    #[serde(deserialize_with = "color")]
+    pub day: Color,
+    /// Items after sunset
+    #[serde(deserialize_with = "color")]
+    pub dusk: Color,
+    #[serde(deserialize_with = "color")]
+    pub night: Color,
+    /// Background of search matches
+    #[serde(deserialize_with = "color")]
+    pub search: Color,
+    /// Background of the item at the current time
+    #[serde(deserialize_with = "color")]
+    pub now: Color,
+    /// The now line between items
+    #[serde(deserialize_with = "color")]
+    pub now_line: Color,
+    /// Background of the lines selected in visual mode
+    #[serde(deserialize_with = "color")]
+    pub selection: Color,
+    /// Times of items that overlap or are out of order
+    #[serde(deserialize_with = "color")]
+    pub conflict: Color,
+    /// Times of items after a long gap
+    #[serde(deserialize_with = "color")]
+    pub gap: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            pre_dawn: Color::Rgb {
+                r: 235,
+                g: 180,
+                b: 180,
+            },
+            day: Color::Reset,
+            dusk: Color::Rgb {
+                r: 215,
+                g: 180,
+                b: 220,
+            },
+            night: Color::Rgb {
+                r: 100,
+                g: 100,
+                b: 255,
+            },
+            search: Color::Rgb {
+                r: 240,
+                g: 200,
+                b: 80,
+            },
+            now: Color::Rgb {
+                r: 50,
+                g: 70,
+                b: 90,
+            },
+            now_line: Color::Rgb {
+                r: 90,
+                g: 160,
+                b: 220,
+            },
+            selection: Color::Rgb {
+                r: 70,
+                g: 70,
+                b: 120,
+            },
+            conflict: Color::Rgb {
+                r: 230,
+                g: 80,
+                b: 80,
+            },
+            gap: Color::Rgb {
+                r: 220,
+                g: 150,
+                b: 60,
+            },
+        }
+    }
+}
+
+/// Parses a color like "#ebb4b4", "dark_cyan" or "reset".
+fn parse_color(s: &str) -> Result<Color, String> {
+    let invalid = || format!("'{}': expected a color like #ebb4b4 or dark_cyan", s);
+
+    if let Some(hex) = s.strip_prefix('#') {
+        if hex.len() != 6 || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        let channel =
+            |idx: usize| u8::from_str_radix(&hex[idx..idx + 2], 16).map_err(|_| invalid());
+        return Ok(Color::Rgb {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        });
+    }
+
+    if s.eq_ignore_ascii_case("reset") {
+        return Ok(Color::Reset);
+    }
+
+    Color::try_from(s).map_err(|_| invalid())
+}
+
+fn color<'de, D>(deserializer: D) -> Result<Color, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    parse_color(&s).map_err(serde::de::Error::custom)
+}
+
+#[test]
+fn parse_colors() {
+    assert_eq!(
+        parse_color("#ebb4b4"),
+        Ok(Color::Rgb {
+            r: 0xeb,
+            g: 0xb4,
+            b: 0xb4
+        })
+    );
+    assert_eq!(parse_color("reset"), Ok(Color::Reset));
+    assert!(parse_color("#ebb4b").is_err());
+    // Not split inside a multi-byte character
+    assert!(parse_color("#aä111").is_err());
+}
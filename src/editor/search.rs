@@ -0,0 +1,179 @@
+//! Searching the schedule for text in summaries, or for the item covering a
+//! time like "14:" or "14:30".
+use std::{fmt, ops::Range};
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{cursor::MappedPos, text_capture::TextCapture};
+use crate::{schedule::Schedule, time::Time};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Search {
+    /// Text in summaries, ignoring case unless it has upper case letters
+    Text(String),
+    /// The item covering a time
+    Time(Time),
+}
+
+/// A search being typed on the status bar
+#[derive(Debug)]
+pub struct SearchInput {
+    pub text: TextCapture,
+    /// Where the cursor was when the search started, returned to on cancel
+    pub origin: MappedPos,
+    /// The search before this one, restored on cancel
+    pub previous: Option<Search>,
+}
+
+impl Search {
+    /// Creates a search from what was typed after "/", None if it is empty
+    pub fn new(pattern: &str) -> Option<Search> {
+        if pattern.is_empty() {
+            None
+        } else {
+            Some(parse_time(pattern).map_or_else(|| Search::Text(pattern.to_owned()), Search::Time))
+        }
+    }
+
+    /// Columns of the matches in a summary, in graphemes
+    pub fn text_matches(&self, summary: &str) -> Vec<Range<usize>> {
+        let pattern = match self {
+            Search::Text(pattern) => pattern,
+            Search::Time(_) => return vec![],
+        };
+
+        // Smart case
+        let ignore_case = !pattern.chars().any(char::is_uppercase);
+        let fold = |grapheme: &str| {
+            if ignore_case {
+                grapheme.to_lowercase()
+            } else {
+                grapheme.to_owned()
+            }
+        };
+        let text: Vec<String> = summary.graphemes(true).map(fold).collect();
+        let pattern: Vec<String> = pattern.graphemes(true).map(fold).collect();
+
+        let mut matches = vec![];
+        let mut col = 0;
+        while col + pattern.len() <= text.len() {
+            if text[col..col + pattern.len()] == pattern[..] {
+                matches.push(col..col + pattern.len());
+                col += pattern.len();
+            } else {
+                col += 1;
+            }
+        }
+        matches
+    }
+
+    /// The line of the item covering the searched time. Between items, the
+    /// item after the time is taken.
+    pub fn time_line(&self, schedule: &Schedule) -> Option<usize> {
+        match self {
+            Search::Time(time) => schedule.line_at(time).or_else(|| schedule.line_after(time)),
+            Search::Text(_) => None,
+        }
+    }
+
+    /// Start positions of all matches in the schedule, in order
+    pub fn positions(&self, schedule: &Schedule) -> Vec<MappedPos> {
+        match self {
+            Search::Text(_) => schedule
+                .timeboxes
+                .iter()
+                .enumerate()
+                .flat_map(|(line, time_box)| {
+                    self.text_matches(&time_box.activity.summary)
+                        .into_iter()
+                        .map(move |cols| MappedPos::new(cols.start, line))
+                })
+                .collect(),
+            Search::Time(_) => self
+                .time_line(schedule)
+                .map(|line| MappedPos::new(0, line))
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// The match after pos, or before it when going backward, wrapping around
+    /// the ends of the schedule
+    pub fn next(&self, schedule: &Schedule, pos: &MappedPos, forward: bool) -> Option<MappedPos> {
+        let positions = self.positions(schedule);
+        let key = |p: &MappedPos| (p.line, p.col);
+        if forward {
+            positions
+                .iter()
+                .find(|p| key(p) > key(pos))
+                .or_else(|| positions.first())
+        } else {
+            positions
+                .iter()
+                .rev()
+                .find(|p| key(p) < key(pos))
+                .or_else(|| positions.last())
+        }
+        .cloned()
+    }
+}
+
+impl fmt::Display for Search {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Search::Text(pattern) => f.write_str(pattern),
+            Search::Time(time) => time.fmt(f),
+        }
+    }
+}
+
+/// Parses a time being typed like "9:", "14:3" or "14:30"
+fn parse_time(pattern: &str) -> Option<Time> {
+    let (hour, min) = pattern.split_at(pattern.find(':')?);
+    let min = &min[1..];
+    let is_digits =
+        |s: &str, max_len: usize| s.len() <= max_len && s.chars().all(|c| c.is_ascii_digit());
+    if hour.is_empty() || !is_digits(hour, 2) || !is_digits(min, 2) {
+        return None;
+    }
+
+    let hour: u8 = hour.parse().ok()?;
+    // "14:3" is on its way to "14:30"
+    let min: u8 = format!("{:0<2}", min).parse().ok()?;
+    if hour >= 24 || min >= 60 {
+        return None;
+    }
+    Some(Time::hm(hour, min))
+}
+
+#[test]
+fn search_text_and_time() {
+    use std::str::FromStr;
+
+    let schedule = Schedule::from_str(
+        "- [ ] 09:00 Work on the plan\n- [ ] 12:00--13:00 Lunch\n- [ ] 14:00 Plan walks\n",
+    )
+    .unwrap();
+
+    let search = Search::new("plan").unwrap();
+    assert_eq!(search.text_matches("Plan a plan"), vec![0..4, 7..11]);
+    assert_eq!(
+        Search::new("Plan").unwrap().text_matches("plan Plan"),
+        vec![5..9]
+    );
+    let first = MappedPos::new(12usize, 0);
+    let second = search.next(&schedule, &first, true).unwrap();
+    assert_eq!((second.col, second.line), (0, 2));
+    let wrapped = search.next(&schedule, &second, true).unwrap();
+    assert_eq!((wrapped.col, wrapped.line), (12, 0));
+    let back = search.next(&schedule, &first, false).unwrap();
+    assert_eq!((back.col, back.line), (0, 2));
+
+    let time_line = |pattern: &str| Search::new(pattern).unwrap().time_line(&schedule);
+    assert_eq!(Search::new("14:"), Some(Search::Time(Time::hm(14, 0))));
+    assert_eq!(Search::new("1:x"), Some(Search::Text("1:x".to_owned())));
+    assert_eq!(time_line("10:"), Some(0));
+    assert_eq!(time_line("12:3"), Some(1));
+    // Between lunch and the walk
+    assert_eq!(time_line("13:30"), Some(2));
+    assert_eq!(time_line("23:"), Some(2));
+}
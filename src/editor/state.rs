@@ -1,27 +1,49 @@
 use super::{
-    command::{self, Command},
-    command_input::CommandInput,
-    cursor::ContentCursor,
-    render::Render,
-    Result,
+    command::{self, Command, KeyMatch, PendingKeys},
+    command_input::{Captured, CommandInput},
+    cursor::{ContentCursor, MappedPos},
+    events::{EventSource, TerminalEvents},
+    graphemes,
+    history::History,
+    keymap::Keymap,
+    motion,
+    registers::{self, Registers},
+    render::{truncate, Buffer, Compositor, Render, Theme},
+    search::{Search, SearchInput},
+    text_capture::{self, TextCapture},
+    viewport::Viewport,
+    Error, Result,
 };
 use crate::{
+    diagnostics::{self, Diagnostic},
     dom::{timebox::AdjustPolicy, TimeBox, TimeSlotKind},
     editor::Mode,
     schedule::Schedule,
-    time::Duration,
+    time::{Duration, Time},
+    twilight::TimeZone,
 };
 use crossterm::{
-    cursor,
-    event::{read, Event},
-    style, terminal, ExecutableCommand, QueueableCommand,
+    event::{Event, KeyCode, KeyEvent},
+    style::ContentStyle,
+    QueueableCommand,
 };
+use fs_err as fs;
 use std::{
     cell::RefCell,
     io::{Stdout, Write},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
     rc::{Rc, Weak},
+    str::FromStr,
+    time::Duration as StdDuration,
 };
 
+/// Rows below the schedule: time left, help and the status bar
+const FOOTER_HEIGHT: u16 = 3;
+
+/// How often the clock is checked to move the now line without key presses
+const CLOCK_TICK: StdDuration = StdDuration::from_secs(1);
+
 macro_rules! ref_cell {
     ( $inner:expr ) => {
         Rc::new(RefCell::new($inner))
@@ -29,49 +51,121 @@ macro_rules! ref_cell {
 }
 
 #[derive(Debug)]
-pub struct State {
-    stdout: Stdout,
+pub struct State<W: Write = Stdout, E: EventSource = TerminalEvents> {
+    /// Where the screen is drawn
+    out: W,
+    events: E,
+    /// Columns and rows of the terminal
+    size: (u16, u16),
     cursor: Option<ContentCursor>,
-    /// The y-position of the cursor was when the schedule started to render
-    schedule_y: Rc<RefCell<u16>>,
-    /// The height of the schedule when rendered, based on cursor y-position
-    /// when the schedule stopped rendering
-    schedule_h: Rc<RefCell<u16>>,
+    /// The part of the schedule visible on screen
+    viewport: Rc<RefCell<Viewport>>,
+    /// Draws rendered frames on the terminal
+    compositor: Compositor,
     pub mode: Rc<RefCell<Mode>>,
     pub time_mode: Rc<RefCell<TimeMode>>,
-    parent_mode: Mode,
+    /// Keys typed so far of an unfinished key sequence like "d3j"
+    pending: Rc<RefCell<PendingKeys>>,
+    /// Count typed before the command being run, e.g. 3 of "3dd"
+    count: Option<usize>,
+    /// Register named before the command being run, e.g. 'a' of "ayy
+    register: Option<char>,
     pub schedule: Schedule,
+    /// The file the schedule is saved into
+    pub file: Option<PathBuf>,
+    /// A one-off message shown in the status bar until the next key press
+    message: Rc<RefCell<Option<String>>>,
+    prompt: Rc<RefCell<Option<String>>>,
+    /// Problems found in the schedule when last rendered
+    diagnostics: Rc<RefCell<Vec<Diagnostic>>>,
+    /// Gaps between items longer than this are pointed out
+    pub max_gap: Duration,
+    /// The last search, highlighted and repeated with n and N
+    search: Option<Search>,
+    /// The search being typed in search mode
+    search_input: Option<SearchInput>,
     status_bar: StatusBar,
     time_cursor: usize,
-    clipboard: Option<TimeBox>,
+    /// Line where the selection of visual mode started, the cursor is at its
+    /// other end
+    visual_anchor: usize,
+    /// Items cut and yanked
+    registers: Registers,
+    /// Default length of new spans
+    pub span_len: Duration,
+    /// Colors used when rendering
+    pub theme: Theme,
+    /// Key bindings in effect
+    pub keymap: Keymap,
+    /// Time zone of the current time, the same as sunrise and sunset are in
+    pub time_zone: TimeZone,
+    /// Tells the current time in the time zone, replaceable e.g. in tests
+    pub clock: fn(&TimeZone) -> Time,
+    /// The time the now line was last drawn at
+    shown_now: Option<Time>,
+    history: History,
+    /// Set when a file is opened, whose schedule starts a history of its own
+    opened_file: bool,
     quit: bool,
-    command_input: Option<CommandInput>,
 }
 
-impl State {
-    pub fn with_stdout(stdout: Stdout, schedule: Schedule) -> State {
+impl<W: Write, E: EventSource> State<W, E> {
+    /// Creates an editor drawing into out, with a screen of given columns
+    /// and rows, reading events from events
+    pub fn new(out: W, events: E, size: (u16, u16), mut schedule: Schedule) -> State<W, E> {
+        // E.g. a template without items, the cursor needs a line to be on
+        if schedule.timeboxes.is_empty() {
+            schedule.timeboxes.push(TimeBox::default());
+        }
         let mode = ref_cell!(Mode::Cursor);
         let time_mode = ref_cell!(TimeMode::Relative);
 
-        let schedule_y = ref_cell!(0);
-        let schedule_h = ref_cell!(0);
+        let message = ref_cell!(None);
+        let prompt = ref_cell!(None);
+        let diagnostics = ref_cell!(vec![]);
+        let pending = ref_cell!(PendingKeys::default());
+
+        let viewport = ref_cell!(Viewport::default());
         State {
-            stdout,
+            out,
+            events,
+            size,
             schedule,
-            schedule_y,
-            schedule_h,
+            file: None,
+            viewport,
+            compositor: Compositor::default(),
             cursor: None,
             status_bar: StatusBar {
                 mode: Rc::downgrade(&mode),
                 time_mode: Rc::downgrade(&time_mode),
+                message: Rc::downgrade(&message),
+                pending: Rc::downgrade(&pending),
+                prompt: Rc::downgrade(&prompt),
+                diagnostics: Rc::downgrade(&diagnostics),
             },
+            prompt,
+            diagnostics,
+            max_gap: Duration::hm(1, 0),
+            search: None,
+            search_input: None,
+            pending,
+            count: None,
+            register: None,
+            message,
             mode,
             time_mode,
-            parent_mode: Mode::Cursor,
-            clipboard: None,
+            registers: Registers::default(),
+            span_len: Duration::hm(1, 0),
+            theme: Theme::default(),
+            keymap: Keymap::default(),
+            time_zone: TimeZone::default(),
+            clock: Time::now,
+            shown_now: None,
+            history: History::default(),
+            opened_file: false,
             quit: false,
             time_cursor: 0,
-            command_input: None,
+            visual_anchor: 0,
         }
     }
 
@@ -79,132 +173,364 @@ impl State {
     pub fn run(&mut self) -> Result<()> {
         self.render()?;
 
-        // Create cursor at top-left
-        let cursor = ContentCursor::create_at_top_left(
-            self.schedule_y.clone(),
-            self.schedule_h.clone(),
-            &mut self.stdout,
-            &self.schedule,
-        );
-        self.cursor = Some(cursor);
+        // Create cursor at top-left, unless continuing an earlier run
+        if self.cursor.is_none() {
+            let cursor = ContentCursor::create_at_top_left(self.viewport.clone(), &self.schedule);
+            self.cursor = Some(cursor);
+        }
 
         // Detect keys until exit
         self.loop_input()
     }
 
-    /// Main re-draw function
+    /// Events not yet handled, e.g. to queue more keys before running again
+    pub fn events_mut(&mut self) -> &mut E {
+        &mut self.events
+    }
+
+    /// Text on each row of the screen as last drawn
+    pub fn screen(&self) -> Vec<String> {
+        self.compositor
+            .frame()
+            .map(|frame| (0..frame.height()).map(|y| frame.row_text(y)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Everything written to the terminal so far
+    pub fn output(&self) -> &W {
+        &self.out
+    }
+
+    /// True once the editor has been told to quit
+    pub fn has_quit(&self) -> bool {
+        self.quit
+    }
+
+    /// Column and line of the cursor in the schedule
+    pub fn cursor_pos(&self) -> Option<MappedPos> {
+        self.cursor
+            .as_ref()
+            .map(|cursor| cursor.map_to_content(&self.schedule))
+    }
+
+    /// Main re-draw function. Lays the screen out again to fit the terminal,
+    /// so it also handles resizing.
     fn render(&mut self) -> Result<()> {
+        let (columns, rows) = self.size;
+
+        // The line of the cursor before relayout
+        let cursor_line = self.cursor.as_ref().map(|cursor| cursor.map_to_line());
+
+        // The schedule gets the rows left over from the footer and the status
+        // bar, and from the now line
+        let now = (self.clock)(&self.time_zone);
         {
-            // Rename binding, we all know what stdout is
-            let mut stdout = &mut self.stdout;
-
-            // Clear screen and move cursor to top-left
-            stdout.execute(terminal::Clear(terminal::ClearType::All))?;
-            stdout.queue(cursor::MoveTo(0, 0))?;
-
-            // Render schedule while measuring it's height
-            let y = cursor::position()?.1;
-            self.schedule_y.replace(y);
-            self.schedule.render(&mut stdout)?;
-            let h = cursor::position()?.1 - y;
-            self.schedule_h.replace(h);
-
-            if let Some(last_timed_item) = self
-                .schedule
-                .timeboxes
-                .iter()
-                .rev()
-                .find_map(|time_box| time_box.time.clone())
-            {
-                let last_time = match &last_timed_item {
-                    TimeSlotKind::Time(t) => t,
-                    TimeSlotKind::Span(_, end) => end,
-                };
-                let time_left: Duration = &self.schedule.wake_up_tomorrow - last_time;
-                stdout
-                    .queue(style::Print(format!(
+            let mut viewport = self.viewport.borrow_mut();
+            viewport.separator = self.schedule.now_separator(&now);
+            viewport.y = 0;
+            viewport.height = rows.saturating_sub(FOOTER_HEIGHT);
+            viewport.width = columns;
+            viewport.clamp(self.schedule.timeboxes.len());
+        }
+        if let (Some(cursor), Some(line)) = (self.cursor.as_mut(), cursor_line) {
+            cursor.relayout(line, &self.schedule);
+        }
+
+        let mut buf = Buffer::new(columns, rows);
+
+        let viewport = *self.viewport.borrow();
+        let visible = viewport.visible(self.schedule.timeboxes.len());
+        self.schedule
+            .render_lines(&mut buf, viewport.y, &self.theme, visible.clone())?;
+        self.schedule
+            .render_now(&mut buf, viewport.y, &self.theme, visible.clone(), &now);
+        let diagnostics = diagnostics::check(&self.schedule, &self.max_gap);
+        self.schedule.render_diagnostics(
+            &mut buf,
+            viewport.y,
+            &self.theme,
+            visible.clone(),
+            &diagnostics,
+        );
+        self.diagnostics.replace(diagnostics);
+        if let Some(lines) = self.selection() {
+            self.schedule.render_selection(
+                &mut buf,
+                viewport.y,
+                &self.theme,
+                visible.clone(),
+                lines,
+            );
+        }
+        if let Some(search) = &self.search {
+            self.schedule
+                .render_matches(&mut buf, viewport.y, &self.theme, visible, search);
+        }
+        // The lines below the now line move down a row to make room for it
+        if let Some(row) = viewport.separator_row() {
+            buf.insert_row(row, viewport.y + viewport.height);
+            self.schedule
+                .render_now_line(&mut buf, row, &self.theme, &now);
+        }
+        self.shown_now = Some(now);
+
+        // The footer and the status bar stick to the bottom rows
+        let footer_y = rows.saturating_sub(FOOTER_HEIGHT);
+        if let Some(last_timed_item) = self
+            .schedule
+            .timeboxes
+            .iter()
+            .rev()
+            .find_map(|time_box| time_box.time.clone())
+        {
+            let last_time = match &last_timed_item {
+                TimeSlotKind::Time(t) => t,
+                TimeSlotKind::Span(_, end) => end,
+            };
+            let time_left: Duration = &self.schedule.wake_up_tomorrow - last_time;
+            buf.set_str(
+                0,
+                footer_y,
+                &truncate(
+                    &format!(
                         "{} left unscheduled / sleep, wake-up at {}",
                         time_left,
                         last_time + &time_left
-                    )))?
-                    .queue(cursor::MoveToNextLine(1))?;
-            }
+                    ),
+                    columns as usize,
+                ),
+                ContentStyle::default(),
+            );
+        }
+        buf.set_str(0, footer_y + 1, "ctrl+q to exit", ContentStyle::default());
 
-            stdout
-                .queue(style::Print("ctrl+q to exit"))?
-                .queue(cursor::MoveToNextLine(1))?;
+        self.status_bar
+            .render(&mut buf, footer_y + 2, &self.theme)?;
 
-            self.status_bar.render(&mut stdout)?;
+        // Only what changed since the previous frame is drawn
+        self.compositor.present(buf, &mut self.out)?;
 
-            stdout.flush()?;
-        }
+        self.place_cursor()
+    }
 
-        if let Some(cursor) = self.cursor.as_mut() {
-            cursor.redraw(&mut self.stdout)?;
+    /// Puts the terminal cursor on the schedule, or on the status bar while a
+    /// search is typed
+    fn place_cursor(&mut self) -> Result<()> {
+        if let Some(input) = &self.search_input {
+            let x = 1 + input.text.cursor_x();
+            self.out
+                .queue(crossterm::cursor::MoveTo(x, self.size.1.saturating_sub(1)))?
+                .flush()?;
+        } else if let Some(cursor) = self.cursor.as_ref() {
+            cursor.redraw(&mut self.out)?;
         }
-
         Ok(())
     }
 
     /// Main input processing loop
     fn loop_input(&mut self) -> Result<()> {
         loop {
-            let ev = read()?;
+            // Wait for the next key of an unfinished sequence only until it
+            // times out
+            let timeout = self.pending.borrow().timeout();
+            // Otherwise wake up now and then to move the now line
+            let tick = timeout.is_none() && !self.events.is_finished();
+            let ev = self
+                .events
+                .next_event(if tick { Some(CLOCK_TICK) } else { timeout })?;
+            if ev.is_none() && timeout.is_none() && !tick {
+                // The event source ran out
+                break;
+            }
+
             let redraw = match ev {
-                Event::Key(key_ev) => {
-                    // Determine command
-                    let editor_command = Command::map(key_ev, self);
-                    let mut redraw = false;
-
-                    // Return to parent mode on command for transient modes like 'g' and 'd'
-                    if self.mode.borrow().is_transient() {
-                        self.mode.replace(self.parent_mode.clone());
-                        redraw = true;
-                    }
+                // Redraw only once the minute changes
+                None if tick => self.shown_now != Some((self.clock)(&self.time_zone)),
+                None => {
+                    let key_match = self
+                        .keymap
+                        .trie
+                        .time_out(&mut self.pending.borrow_mut(), &self.mode.borrow());
+                    self.handle_key_match(key_match)?;
+                    // Clear the pending keys from the status bar
+                    true
+                }
+                Some(Event::Key(key_ev)) => {
+                    // Messages are only shown until the next key press
+                    let mut redraw = self.message.replace(None).is_some();
 
-                    redraw |= if let Some(cmd) = editor_command {
-                        let redraw = self.act(&cmd)?;
-                        if self.quit {
-                            break;
-                        }
-                        redraw
-                    }
-                    // No command was found for this key
-                    else {
-                        // Insert mode: make edits to the schedule data-structure
-                        if *self.mode.borrow() == Mode::Insert {
-                            let cursor = self
-                                .cursor
-                                .as_mut()
-                                .expect("must have cursor when editing schedule");
-                            let schedule = &mut self.schedule;
-                            schedule.edit_content(&key_ev, cursor, &mut self.stdout)?
-                        } else {
-                            // redraw
-                            false
-                        }
-                    };
+                    // Moving the cursor out of view scrolls the schedule
+                    let offset = self.viewport.borrow().offset;
+
+                    let was_pending = !self.pending.borrow().is_empty();
+                    let key_match = self.keymap.trie.feed(
+                        &mut self.pending.borrow_mut(),
+                        key_ev,
+                        &self.mode.borrow(),
+                    );
+                    redraw |= self.handle_key_match(key_match)?;
+
+                    // Show or clear the pending keys in the status bar
+                    redraw |= was_pending || !self.pending.borrow().is_empty();
+
+                    redraw |= self.viewport.borrow().offset != offset;
 
                     redraw
                 }
-                Event::Mouse(_) => {
+                Some(Event::Mouse(_)) => {
                     // redraw
                     false
                 }
-                Event::Resize(_, _) => {
-                    eprintln!("resize not implemented");
-                    // redraw
-                    false
+                Some(Event::Resize(columns, rows)) => {
+                    // Lay out for the new size
+                    self.size = (columns, rows);
+                    true
                 }
             };
+            if self.quit {
+                break;
+            }
             if redraw {
                 self.render()?;
+            } else {
+                // Show cursor moves that did not need a full redraw
+                self.place_cursor()?;
             }
         }
 
         Ok(())
     }
 
+    /// Runs the command of a completed key sequence, or types the keys in
+    /// insert or search mode if they are not bound. Returns "need full
+    /// redraw".
+    fn handle_key_match(&mut self, key_match: KeyMatch) -> Result<bool> {
+        match key_match {
+            KeyMatch::Command(cmd, count, register) => {
+                self.register = register;
+                let redraw = self.run_command(&cmd, count);
+                self.register = None;
+                redraw
+            }
+            KeyMatch::Pending => Ok(false),
+            KeyMatch::Unbound(keys) => {
+                let mut redraw = false;
+                // Insert mode: make edits to the schedule data-structure,
+                // search mode: edit the search
+                if self.mode.borrow().types_text() {
+                    for key_ev in keys {
+                        redraw |= self.type_key(&key_ev)?;
+                    }
+                }
+                Ok(redraw)
+            }
+        }
+    }
+
+    /// Runs a command count times, or once with the count if the command
+    /// takes one. Returns "need full redraw".
+    fn run_command(&mut self, cmd: &Command, count: Option<usize>) -> Result<bool> {
+        // Snapshot of the schedule for undo history
+        let before = self.schedule.clone();
+
+        let mut redraw = false;
+        if cmd.takes_count() {
+            self.count = count;
+            redraw = self.act(cmd)?;
+            self.count = None;
+        } else {
+            for _ in 0..count.unwrap_or(1) {
+                redraw |= self.act(cmd)?;
+                if self.quit {
+                    break;
+                }
+            }
+        }
+
+        // Each edit by command is its own undo step
+        match cmd {
+            Command::Undo | Command::Redo => {}
+            _ if std::mem::take(&mut self.opened_file) => {}
+            _ if self.schedule != before => self.history.record(before),
+            _ => self.history.break_coalescing(),
+        }
+
+        Ok(redraw)
+    }
+
+    /// Types a key into the activity under the cursor
+    fn type_key(&mut self, key_ev: &KeyEvent) -> Result<bool> {
+        if self.search_input.is_some() {
+            return self.type_search(key_ev);
+        }
+
+        let before = self.schedule.clone();
+
+        let cursor = self
+            .cursor
+            .as_mut()
+            .expect("must have cursor when editing schedule");
+        let redraw = self.schedule.edit_content(key_ev, cursor)?;
+
+        // Consecutive keystrokes are undone together
+        if self.schedule != before {
+            self.history.record_coalesced(before);
+        }
+
+        Ok(redraw)
+    }
+
+    /// Edits the search being typed and moves the cursor to the first match
+    /// after where the search started. Returns "need full redraw".
+    fn type_search(&mut self, key_ev: &KeyEvent) -> Result<bool> {
+        let input = self.search_input.as_mut().expect("must be typing a search");
+
+        // Backspace on empty input cancels, like on the command line
+        if key_ev.code == KeyCode::Backspace && input.text.text().is_empty() {
+            return self.act(&Command::CancelSearch);
+        }
+        input.text.input(key_ev);
+
+        let text = input.text.text().clone();
+        let origin = input.origin.clone();
+        self.search = Search::new(&text);
+        self.prompt.replace(Some(format!("/{}", text)));
+
+        let target = self
+            .search
+            .as_ref()
+            .and_then(|search| search.next(&self.schedule, &origin, true))
+            .unwrap_or(origin);
+        self.cursor
+            .as_mut()
+            .expect("must have cursor")
+            .move_to_content(&target, &self.schedule);
+
+        Ok(true)
+    }
+
+    /// Moves the cursor to the next match of the last search
+    fn search_next(&mut self, forward: bool) {
+        let search = match &self.search {
+            Some(search) => search,
+            None => {
+                self.message.replace(Some("no previous search".to_owned()));
+                return;
+            }
+        };
+        let cursor = self.cursor.as_mut().expect("must have cursor");
+        let pos = cursor.map_to_content(&self.schedule);
+        match search.next(&self.schedule, &pos, forward) {
+            Some(target) => {
+                cursor.move_to_content(&target, &self.schedule);
+            }
+            None => {
+                self.message
+                    .replace(Some(format!("pattern not found: {}", search)));
+            }
+        }
+    }
+
     /// Returns "need full redraw"
     fn act(&mut self, cmd: &Command) -> Result<bool> {
         let redraw = match cmd {
@@ -217,20 +543,42 @@ impl State {
                 match dir {
                     command::MoveCursor::Dir(dir) => match dir {
                         command::Dir::Up => {
-                            cursor.move_up(&self.schedule, &mut self.stdout)?;
+                            cursor.move_up(&self.schedule);
                         }
                         command::Dir::Down => {
-                            cursor.move_down(&self.schedule, &mut self.stdout)?;
+                            cursor.move_down(&self.schedule);
                         }
                         command::Dir::Left => {
-                            cursor.move_left(&self.schedule, &mut self.stdout)?;
+                            cursor.move_left(&self.schedule);
                         }
                         command::Dir::Right => {
-                            cursor.move_right(&self.schedule, &mut self.stdout)?;
+                            cursor.move_right(&self.schedule);
                         }
                     },
-                    command::MoveCursor::Top => unimplemented!(),
-                    command::MoveCursor::Bottom => unimplemented!(),
+                    command::MoveCursor::PageUp | command::MoveCursor::PageDown => {
+                        let page = self.viewport.borrow().height.max(1) as usize;
+                        let line = cursor.map_to_line();
+                        let line = match dir {
+                            command::MoveCursor::PageUp => line.saturating_sub(page),
+                            _ => line + page,
+                        };
+                        self.move_cursor_to_line(line);
+                    }
+                    command::MoveCursor::Top | command::MoveCursor::Bottom => {
+                        // Count is a line number starting from 1
+                        let line = match (self.count, dir) {
+                            (Some(count), _) => count - 1,
+                            (None, command::MoveCursor::Top) => 0,
+                            (None, _) => self.schedule.timeboxes.len() - 1,
+                        };
+                        self.move_cursor_to_line(line);
+                    }
+                    motion => {
+                        let pos = cursor.map_to_content(&self.schedule);
+                        if let Some(target) = motion::target(motion, &pos, &self.schedule) {
+                            cursor.move_to_content(&target, &self.schedule);
+                        }
+                    }
                 };
                 // Redraw
                 false
@@ -240,6 +588,133 @@ impl State {
                 // Redraw
                 true
             }
+            Command::Search => {
+                let origin = self
+                    .cursor
+                    .as_ref()
+                    .expect("must have cursor")
+                    .map_to_content(&self.schedule);
+                self.search_input = Some(SearchInput {
+                    text: TextCapture::owned(text_capture::InputSource::Stdio(None)),
+                    origin,
+                    previous: self.search.clone(),
+                });
+                self.prompt.replace(Some("/".to_owned()));
+                *self.mode.borrow_mut() = Mode::Search;
+                true
+            }
+            Command::ConfirmSearch => {
+                self.search_input = None;
+                self.prompt.replace(None);
+                *self.mode.borrow_mut() = Mode::Cursor;
+                if let Some(search) = &self.search {
+                    if search.positions(&self.schedule).is_empty() {
+                        self.message
+                            .replace(Some(format!("pattern not found: {}", search)));
+                    }
+                }
+                true
+            }
+            Command::CancelSearch => {
+                if let Some(input) = self.search_input.take() {
+                    self.search = input.previous;
+                    self.cursor
+                        .as_mut()
+                        .expect("must have cursor")
+                        .move_to_content(&input.origin, &self.schedule);
+                }
+                self.prompt.replace(None);
+                *self.mode.borrow_mut() = Mode::Cursor;
+                true
+            }
+            Command::SearchNext => {
+                self.search_next(true);
+                true
+            }
+            Command::SearchPrevious => {
+                self.search_next(false);
+                true
+            }
+            Command::CatchUp => {
+                let now = (self.clock)(&self.time_zone).round_to_quarter();
+                let message = match self.schedule.catch_up(&now) {
+                    Some((line, delta)) => {
+                        self.move_cursor_to_line(line);
+                        let minutes = delta.num_minutes();
+                        let shift =
+                            Duration::hm((minutes.abs() / 60) as i8, (minutes.abs() % 60) as i8);
+                        let mut message = match minutes {
+                            0 => "already on time".to_owned(),
+                            m if m > 0 => format!("shifted {} later", shift),
+                            _ => format!("{} ahead of schedule", shift),
+                        };
+                        let overlapping: Vec<String> = self
+                            .schedule
+                            .overlapping_fixed(line)
+                            .into_iter()
+                            .map(|line| self.schedule.timeboxes[line].activity.summary.clone())
+                            .collect();
+                        if !overlapping.is_empty() {
+                            message += &format!(", overlapping fixed: {}", overlapping.join(", "));
+                        }
+                        message
+                    }
+                    None => "nothing left to catch up".to_owned(),
+                };
+                self.message.replace(Some(message));
+                true
+            }
+            Command::MoveTimeBox { dir, keep_times } => {
+                let line = self
+                    .cursor
+                    .as_ref()
+                    .expect("must have cursor")
+                    .map_to_line();
+                let target = match dir {
+                    command::Dir::Up => line.checked_sub(1),
+                    command::Dir::Down => {
+                        Some(line + 1).filter(|to| *to < self.schedule.timeboxes.len())
+                    }
+                    command::Dir::Left | command::Dir::Right => None,
+                };
+                match target {
+                    Some(to) => {
+                        if *keep_times {
+                            self.schedule.swap_activities(line, to);
+                        } else {
+                            self.schedule.swap(line, to);
+                        }
+                        self.move_cursor_to_line(to);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            Command::JumpToNow => {
+                let now = (self.clock)(&self.time_zone);
+                let line = self
+                    .schedule
+                    .line_at(&now)
+                    .or_else(|| self.schedule.line_after(&now))
+                    .or_else(|| self.schedule.line_before(&now));
+                match line {
+                    Some(line) => self.move_cursor_to_line(line),
+                    None => {
+                        self.message
+                            .replace(Some("no items with a time".to_owned()));
+                        true
+                    }
+                }
+            }
+            Command::VisualMode => {
+                self.visual_anchor = self
+                    .cursor
+                    .as_ref()
+                    .expect("must have cursor")
+                    .map_to_line();
+                *self.mode.borrow_mut() = Mode::Visual;
+                true
+            }
             Command::CursorMode => {
                 *self.mode.borrow_mut() = Mode::Cursor;
                 // Redraw
@@ -256,7 +731,7 @@ impl State {
 
                     if self.schedule.timeboxes[cursor_line].time.is_none() {
                         let inherit_time = TimeSlotKind::inherit_time(cursor_line, &self.schedule);
-                        self.schedule.mut_line_at_cursor(&cursor).time = Some(inherit_time);
+                        self.schedule.mut_line_at_cursor(cursor).time = Some(inherit_time);
 
                         // ... and use absolute mode
                         *self.time_mode.borrow_mut() = TimeMode::Absolute;
@@ -268,15 +743,6 @@ impl State {
                 // Redraw
                 true
             }
-            Command::GoToMode => {
-                // Store current mode as parent mode
-                self.parent_mode = self.mode.borrow().clone();
-
-                *self.mode.borrow_mut() = Mode::GoTo;
-
-                // Redraw
-                true
-            }
             Command::InsertTimeBoxBelow => {
                 // Insert time box below
                 let cursor = self
@@ -288,41 +754,31 @@ impl State {
                 let cursor_line = pos.line + 1;
 
                 self.schedule.insert_time_box(cursor_line)?;
-                // HACK: Increase height to allow cursor to move correct
-                *self.schedule_h.borrow_mut() += 1;
 
                 // Move one down and to the beginning
                 let pos = cursor.map_to_content(&self.schedule);
                 self.cursor
                     .as_mut()
                     .expect("must have cursor")
-                    .move_to_content(
-                        &pos.column(0).next_line(),
-                        &self.schedule,
-                        &mut self.stdout,
-                    )?;
+                    .move_to_content(&pos.column(0).next_line(), &self.schedule);
 
                 // Redraw
                 true
             }
             Command::InsertTimeBoxAbove => {
-                // Insert time box below
+                // Insert time box above
                 let cursor = self
                     .cursor
-                    .as_ref()
+                    .as_mut()
                     .expect("must have cursor when editing schedule");
 
                 let pos = cursor.map_to_content(&self.schedule);
-                let cursor_line = pos.line;
 
-                self.schedule.insert_time_box(cursor_line)?;
+                self.schedule.insert_time_box(pos.line)?;
 
-                // Move one up and to the beginning
-                let pos = cursor.map_to_content(&self.schedule);
-                self.cursor
-                    .as_mut()
-                    .expect("must have cursor")
-                    .move_to_content(&pos.column(0), &self.schedule, &mut self.stdout)?;
+                // The new line takes the place of the cursor line, move to its
+                // beginning
+                cursor.move_to_content(&pos.column(0), &self.schedule);
 
                 // Redraw
                 true
@@ -336,49 +792,109 @@ impl State {
                 }
                 redraw
             }
+            Command::DeleteTo(motion) => {
+                let cursor = self.cursor.as_mut().expect("must have cursor");
+                let pos = cursor.map_to_content(&self.schedule);
+                match motion::target(motion, &pos, &self.schedule) {
+                    // Only text on the cursor line is deleted
+                    Some(target) if target.line == pos.line => {
+                        let start = target.col.min(pos.col);
+                        let end = target.col.max(pos.col);
+                        let deleted = graphemes::remove_range(
+                            &mut self.schedule.timeboxes[pos.line].activity.summary,
+                            start..end,
+                        );
+                        cursor.move_to_content(&pos.column(start), &self.schedule);
+                        deleted
+                    }
+                    _ => false,
+                }
+            }
             Command::GoToColumn(col_kind) => {
                 let cursor = self.cursor.as_mut().unwrap();
                 let cursor_pos = cursor.map_to_content(&self.schedule);
 
                 match col_kind {
-                    command::ColumnKind::Index(idx) => cursor.move_to_content(
-                        &cursor_pos.column(*idx),
-                        &self.schedule,
-                        &mut self.stdout,
-                    )?,
+                    command::ColumnKind::Index(idx) => {
+                        cursor.move_to_content(&cursor_pos.column(*idx), &self.schedule)
+                    }
                     command::ColumnKind::Last => {
-                        let x = self.schedule.timeboxes[cursor_pos.line as usize]
-                            .activity
-                            .summary
-                            .len();
-                        cursor.move_to_content(
-                            &cursor_pos.column(x),
-                            &self.schedule,
-                            &mut self.stdout,
-                        )?
+                        let x = graphemes::count(
+                            &self.schedule.timeboxes[cursor_pos.line].activity.summary,
+                        );
+                        cursor.move_to_content(&cursor_pos.column(x), &self.schedule)
                     }
                 }
             }
-            Command::DeleteMode => {
-                *self.mode.borrow_mut() = Mode::Delete;
+            Command::CutCurrentLine if self.selection().is_some() => {
+                let lines = self.selection().expect("must be in visual mode");
+                self.cut_lines(*lines.start(), *lines.end())?;
+                *self.mode.borrow_mut() = Mode::Cursor;
                 true
             }
             Command::CutCurrentLine => {
-                let cursor = self.cursor.as_ref().unwrap();
-                let cursor_line = cursor.map_to_line();
-
-                let removed = self.schedule.timeboxes.remove(cursor_line as usize);
-                self.cursor
-                    .as_mut()
+                let line = self
+                    .cursor
+                    .as_ref()
                     .expect("must have cursor")
-                    .clamp_to_content(&self.schedule);
-
-                self.clipboard = Some(removed);
-
+                    .map_to_line();
+                let last = line + self.count.unwrap_or(1) - 1;
+                self.cut_lines(line, last)?;
                 true
             }
+            Command::CutLines(dir) => {
+                let line = self
+                    .cursor
+                    .as_ref()
+                    .expect("must have cursor")
+                    .map_to_line();
+                let count = self.count.unwrap_or(1);
+                match dir {
+                    command::Dir::Up => self.cut_lines(line.saturating_sub(count), line)?,
+                    _ => self.cut_lines(line, line + count)?,
+                }
+                true
+            }
+            Command::YankCurrentLine if self.selection().is_some() => {
+                let lines = self.selection().expect("must be in visual mode");
+                self.yank_lines(*lines.start(), *lines.end())?;
+                self.move_cursor_to_line(*lines.start());
+                *self.mode.borrow_mut() = Mode::Cursor;
+                true
+            }
+            Command::YankCurrentLine => {
+                let line = self
+                    .cursor
+                    .as_ref()
+                    .expect("must have cursor")
+                    .map_to_line();
+                let last = line + self.count.unwrap_or(1) - 1;
+                self.yank_lines(line, last)?;
+                false
+            }
+            Command::YankLines(dir) => {
+                let line = self
+                    .cursor
+                    .as_ref()
+                    .expect("must have cursor")
+                    .map_to_line();
+                let count = self.count.unwrap_or(1);
+                match dir {
+                    command::Dir::Up => self.yank_lines(line.saturating_sub(count), line)?,
+                    _ => self.yank_lines(line, line + count)?,
+                }
+                false
+            }
+            Command::YankAll(register) => {
+                if register.is_some() {
+                    self.register = *register;
+                }
+                self.yank_lines(0, self.schedule.timeboxes.len() - 1)?;
+                false
+            }
             Command::PasteBelow => {
-                if let Some(content) = self.clipboard.as_ref() {
+                let items = self.registers.get(self.register);
+                if !items.is_empty() {
                     let cursor = self.cursor.as_ref().unwrap();
                     let mut cursor_line = cursor.map_to_line();
                     if cursor_line >= self.schedule.timeboxes.len() {
@@ -386,35 +902,42 @@ impl State {
                     }
 
                     let sched: &mut Schedule = &mut self.schedule;
-                    sched
-                        .timeboxes
-                        .insert(cursor_line as usize + 1, content.clone());
+                    let at = cursor_line + 1;
+                    sched.timeboxes.splice(at..at, items.iter().cloned());
                     true
                 } else {
                     false
                 }
             }
             Command::PasteAbove => {
-                if let Some(content) = self.clipboard.as_ref() {
+                let items = self.registers.get(self.register);
+                if !items.is_empty() {
                     let cursor = self.cursor.as_mut().unwrap();
                     let cursor_pos = cursor.map_to_content(&self.schedule);
 
+                    let at = cursor_pos.line;
                     self.schedule
                         .timeboxes
-                        .insert(cursor_pos.line, content.clone());
+                        .splice(at..at, items.iter().cloned());
                     true
                 } else {
                     false
                 }
             }
+            Command::AdjustTime { hours, minutes } if self.selection().is_some() => {
+                let lines = self.selection().expect("must be in visual mode");
+                self.schedule
+                    .shift_lines(lines, &Duration::hm(*hours, *minutes));
+                true
+            }
             Command::AdjustTime { hours, minutes } => {
                 let cursor = self.cursor.as_ref().unwrap();
                 let cursor_line = cursor.map_to_line();
 
                 let adjust_duration = Duration::hm(*hours, *minutes);
 
-                let adjust_mode = self.time_mode.borrow();
-                match *adjust_mode {
+                let adjust_mode = self.time_mode.borrow().clone();
+                match adjust_mode {
                     TimeMode::Relative => {
                         let schedule: &mut Schedule = &mut self.schedule;
                         schedule.adjust_times_relative(
@@ -425,11 +948,15 @@ impl State {
                     }
                     TimeMode::Absolute => {
                         let schedule: &mut Schedule = &mut self.schedule;
-                        schedule.adjust_time_absolute(
+                        let line = schedule.adjust_time_absolute(
                             cursor_line,
                             &adjust_duration,
                             self.time_cursor,
                         );
+                        // Follow the item into its new place
+                        if line != cursor_line {
+                            self.move_cursor_to_line(line);
+                        }
                     }
                 }
 
@@ -444,16 +971,18 @@ impl State {
                 false
             }
             Command::DeleteTime => {
-                self.schedule
-                    .mut_line_at_cursor(self.cursor.as_ref().expect("must have cursor"))
-                    .time = None;
+                for time_box in self.target_items_mut() {
+                    time_box.time = None;
+                }
                 true
             }
             Command::ToggleCrossOver => {
-                let line = self
-                    .schedule
-                    .mut_line_at_cursor(&self.cursor.as_ref().expect("must have cursor"));
-                line.done = !line.done;
+                // Mixed lines are all crossed over first
+                let items = self.target_items_mut();
+                let done = !items.iter().all(|time_box| time_box.done);
+                for time_box in items {
+                    time_box.done = done;
+                }
                 true
             }
             Command::SwapTimeSubMode => {
@@ -466,23 +995,36 @@ impl State {
                 true
             }
             Command::ToggleTimeAdjustPolicyFixed => {
-                let time_box = self.item_on_cursor_mut();
+                let items = self.target_items_mut();
+                let all_fixed = items
+                    .iter()
+                    .all(|time_box| time_box.adjust_policy == AdjustPolicy::Fixed);
 
-                let new_policy = match time_box.adjust_policy {
-                    AdjustPolicy::Normal => AdjustPolicy::Fixed,
-                    AdjustPolicy::Fixed => AdjustPolicy::Normal,
+                let new_policy = if all_fixed {
+                    AdjustPolicy::Normal
+                } else {
+                    AdjustPolicy::Fixed
                 };
 
-                time_box.adjust_policy = new_policy;
+                for time_box in items {
+                    time_box.adjust_policy = new_policy.clone();
+                }
 
                 true
             }
+            Command::SetActivityKind(kind) => {
+                for time_box in self.target_items_mut() {
+                    time_box.activity.kind = kind.clone();
+                }
+                true
+            }
             Command::ToggleBetweenSpanAndTime => {
+                let span_len = self.span_len;
                 let time = &self.item_on_cursor_mut().time;
 
                 let ntime = match time {
                     Some(slot) => match slot {
-                        TimeSlotKind::Time(t) => TimeSlotKind::Span(*t, *t),
+                        TimeSlotKind::Time(t) => TimeSlotKind::Span(*t, t + &span_len),
                         TimeSlotKind::Span(start, _end) => TimeSlotKind::Time(*start),
                     },
                     None => {
@@ -500,21 +1042,286 @@ impl State {
                 self.open_command_input()?;
                 true
             }
+            Command::Undo => {
+                match self.history.undo(&self.schedule) {
+                    Some(prev) => self.restore(prev),
+                    None => {
+                        self.message
+                            .replace(Some("already at oldest change".to_owned()));
+                    }
+                }
+                true
+            }
+            Command::Redo => {
+                match self.history.redo(&self.schedule) {
+                    Some(next) => self.restore(next),
+                    None => {
+                        self.message
+                            .replace(Some("already at newest change".to_owned()));
+                    }
+                }
+                true
+            }
+            // Whether saving worked is shown in the status bar
+            Command::Save => {
+                let _ = self.save();
+                true
+            }
+            Command::SaveAs(file) => {
+                self.file = Some(file.clone());
+                let _ = self.save();
+                true
+            }
+            Command::SaveAndQuit => {
+                // Unsaved edits are not thrown away
+                self.quit = self.save().is_ok();
+                true
+            }
+            Command::Edit(file) => {
+                let message = match self.load(file) {
+                    Ok(()) => format!("opened {}", file.display()),
+                    Err(e) => format!("could not open {}: {}", file.display(), e),
+                };
+                self.message.replace(Some(message));
+                true
+            }
+            Command::ShiftTimes {
+                duration,
+                from: None,
+            } if self.selection().is_some() => {
+                let lines = self.selection().expect("must be in visual mode");
+                self.schedule.shift_lines(lines, duration);
+                true
+            }
+            Command::ShiftTimes { duration, from } => {
+                let line = match from {
+                    Some(line) => *line,
+                    None => self
+                        .cursor
+                        .as_ref()
+                        .expect("must have cursor")
+                        .map_to_line(),
+                };
+
+                if line < self.schedule.timeboxes.len() {
+                    self.schedule.adjust_times_relative(line, duration, 0);
+                } else {
+                    self.message.replace(Some(format!("no line {}", line + 1)));
+                }
+                true
+            }
+            Command::Sort => {
+                let cursor_line = self
+                    .cursor
+                    .as_ref()
+                    .expect("must have cursor")
+                    .map_to_line();
+                let order = self.schedule.sort();
+                // Follow the item under the cursor
+                if let Some(line) = order.iter().position(|old| *old == cursor_line) {
+                    self.move_cursor_to_line(line);
+                }
+                true
+            }
+            Command::Set(setting) => {
+                let message = match setting {
+                    command::Setting::SpanLength(duration) => {
+                        self.span_len = *duration;
+                        format!("span = {}", duration)
+                    }
+                    command::Setting::MaxGap(duration) => {
+                        self.max_gap = *duration;
+                        format!("gap = {}", duration)
+                    }
+                };
+                self.message.replace(Some(message));
+                true
+            }
         };
         Ok(redraw)
     }
 
+    /// Moves the cursor to given line, keeping the column where possible
+    fn move_cursor_to_line(&mut self, line: usize) -> bool {
+        let line = line.min(self.schedule.timeboxes.len() - 1);
+        self.cursor
+            .as_mut()
+            .expect("must have cursor")
+            .move_to_line(line, &self.schedule)
+    }
+
+    /// Cuts lines from first to last, inclusive, into the register named for
+    /// the command. The cursor moves to the line after the cut.
+    fn cut_lines(&mut self, first: usize, last: usize) -> Result<()> {
+        let last = last.min(self.schedule.timeboxes.len() - 1);
+        let items = self.schedule.timeboxes.drain(first..=last).collect();
+        self.store(items)?;
+
+        // The schedule always has at least one line for the cursor to be on
+        if self.schedule.timeboxes.is_empty() {
+            self.schedule.timeboxes.push(TimeBox::default());
+        }
+
+        self.cursor
+            .as_mut()
+            .expect("must have cursor")
+            .clamp_to_content(&self.schedule);
+        self.move_cursor_to_line(first);
+        Ok(())
+    }
+
+    /// Copies lines from first to last, inclusive, into the register named
+    /// for the command
+    fn yank_lines(&mut self, first: usize, last: usize) -> Result<()> {
+        let last = last.min(self.schedule.timeboxes.len() - 1);
+        let items: Vec<TimeBox> = self.schedule.timeboxes[first..=last].to_vec();
+        if items.len() > 1 {
+            self.message
+                .replace(Some(format!("{} items yanked", items.len())));
+        }
+        self.store(items)
+    }
+
+    /// Puts cut or yanked items into the register named for the command. The
+    /// clipboard register is copied to the system clipboard as Markdown with
+    /// an OSC 52 escape sequence, which most terminals support.
+    fn store(&mut self, items: Vec<TimeBox>) -> Result<()> {
+        let clipboard = matches!(self.register, Some(registers::CLIPBOARD | '*'));
+        if clipboard {
+            let markdown = items
+                .iter()
+                .map(TimeBox::to_markdown)
+                .collect::<Vec<_>>()
+                .join("\n");
+            write!(self.out, "\x1b]52;c;{}\x07", base64::encode(markdown))?;
+            self.out.flush()?;
+            self.message.replace(Some(format!(
+                "copied {} item{} to the clipboard",
+                items.len(),
+                if items.len() == 1 { "" } else { "s" }
+            )));
+        }
+        self.registers.set(self.register, items);
+        Ok(())
+    }
+
+    /// Replaces the schedule with one from history, keeping the cursor on
+    /// content
+    fn restore(&mut self, schedule: Schedule) {
+        self.schedule = schedule;
+        self.cursor
+            .as_mut()
+            .expect("must have cursor")
+            .clamp_to_content(&self.schedule);
+    }
+
+    /// Writes the schedule into the current file as Markdown, reporting the
+    /// result in the status bar
+    fn save(&mut self) -> Result<()> {
+        let file = match &self.file {
+            Some(file) => file,
+            None => {
+                self.message.replace(Some(Error::NoFile.to_string()));
+                return Err(Error::NoFile);
+            }
+        };
+        let result = Self::write_file(file, &self.schedule);
+        let message = match &result {
+            Ok(()) => format!("saved {}", file.display()),
+            Err(e) => format!("could not save: {}", e),
+        };
+        self.message.replace(Some(message));
+        result
+    }
+
+    fn write_file(file: &Path, schedule: &Schedule) -> Result<()> {
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(file, schedule.to_markdown())?;
+        Ok(())
+    }
+
+    /// Replaces the schedule with one read from given file. The new schedule
+    /// is saved into the same file.
+    fn load(&mut self, file: &Path) -> Result<()> {
+        let text = fs::read_to_string(file)?;
+        let mut schedule = Schedule::from_str(&text)?;
+        schedule.sunrise = self.schedule.sunrise;
+        schedule.sunset = self.schedule.sunset;
+
+        self.file = Some(file.to_owned());
+        self.restore(schedule);
+        // Like in Vim, the changes of the previous file can not be undone
+        self.history = History::default();
+        self.opened_file = true;
+        Ok(())
+    }
+
     fn open_command_input(&mut self) -> Result<()> {
         let mut input = CommandInput::default();
 
-        if let Some(cmd) = input.capture(&mut self.stdout)? {
-            self.act(&cmd)?;
-            self.render().expect("cannot redraw");
+        let text = loop {
+            let row = self.size.1.saturating_sub(1);
+            match input.capture(&mut self.out, &mut self.events, row)? {
+                Captured::Done(text) => break text,
+                Captured::Resized(columns, rows) => {
+                    // Lay out for the new size, the input goes on the new
+                    // bottom row
+                    self.size = (columns, rows);
+                    self.render()?;
+                }
+            }
+        };
+
+        // The input line was drawn over the screen
+        self.compositor.invalidate();
+
+        if let Some(text) = text {
+            match Command::from_str(&text) {
+                Ok(cmd) => {
+                    self.act(&cmd)?;
+                }
+                Err(e) => {
+                    self.message.replace(Some(e.to_string()));
+                }
+            }
         }
 
         Ok(())
     }
 
+    /// Lines selected in visual mode, None in other modes
+    fn selection(&self) -> Option<RangeInclusive<usize>> {
+        if *self.mode.borrow() != Mode::Visual {
+            return None;
+        }
+        let line = self
+            .cursor
+            .as_ref()
+            .expect("must have cursor")
+            .map_to_line();
+        let anchor = self.visual_anchor.min(self.schedule.timeboxes.len() - 1);
+        Some(anchor.min(line)..=anchor.max(line))
+    }
+
+    /// Items a command acts on: the selection in visual mode, otherwise the
+    /// item under the cursor
+    fn target_items_mut(&mut self) -> &mut [TimeBox] {
+        let lines = match self.selection() {
+            Some(lines) => lines,
+            None => {
+                let line = self
+                    .cursor
+                    .as_ref()
+                    .expect("must have cursor")
+                    .map_to_line();
+                line..=line
+            }
+        };
+        &mut self.schedule.timeboxes[lines]
+    }
+
     fn item_on_cursor_mut(&mut self) -> &mut TimeBox {
         let cursor_line = self
             .cursor
@@ -529,6 +1336,13 @@ impl State {
 pub struct StatusBar {
     pub mode: Weak<RefCell<Mode>>,
     pub time_mode: Weak<RefCell<TimeMode>>,
+    pub message: Weak<RefCell<Option<String>>>,
+    pub pending: Weak<RefCell<PendingKeys>>,
+    /// Text being typed on the status bar, e.g. a search, shown instead of
+    /// the mode and the message
+    pub prompt: Weak<RefCell<Option<String>>>,
+    /// Problems found in the schedule, summed up on the right
+    pub diagnostics: Weak<RefCell<Vec<Diagnostic>>>,
 }
 #[derive(Clone, Debug, PartialEq)]
 pub enum TimeMode {
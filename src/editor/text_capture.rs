@@ -2,17 +2,18 @@ use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use std::{
     cell::{Ref, RefCell},
     fmt,
-    io::{self, Stdout, Write},
+    io::{self, Write},
     rc::Rc,
 };
 
-use super::render::Draw;
+use super::{graphemes, render::Draw};
 
 /// Captures and maintains text input and a character cursor from key events passed to it. Can maintain it's own buffer or borrow an external one.
 #[derive(Debug)]
 pub struct TextCapture {
     text: Rc<RefCell<String>>,
     cursor: usize,
+    #[allow(dead_code)]
     input: InputSource,
 }
 
@@ -24,7 +25,9 @@ pub enum InputSource {
     Key(InputFn),
 }
 
-pub struct InputFn(Box<dyn Fn(&KeyEvent) -> (bool, i32)>);
+type KeyInputFn = dyn Fn(&KeyEvent) -> (bool, i32);
+
+pub struct InputFn(#[allow(dead_code)] Box<KeyInputFn>);
 
 impl fmt::Debug for InputFn {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -61,35 +64,24 @@ impl TextCapture {
         match code {
             // Insert char and move cursor right
             KeyCode::Char(c) => {
-                let mut c = *c;
-
-                // HACK: work around hard to manage multi-codepoint unicode characters
-                if c.len_utf8() != 1 {
-                    c = match c {
-                        'ä' => 'a',
-                        'ö' => 'o',
-                        // Return for a no-op on unknown multi-codepoint character
-                        _ => {
-                            return (false, 0);
-                        }
-                    };
-                }
-
                 // Make into capital letter if shift is pressed
-                if modifiers.intersects(KeyModifiers::SHIFT) {
-                    c = c.to_uppercase().next().expect("programmer logic error");
+                let text = if modifiers.intersects(KeyModifiers::SHIFT) {
+                    c.to_uppercase().collect()
+                } else {
+                    c.to_string()
                 };
 
-                // Insert the character into captured text at cursor position
-                self.text.borrow_mut().insert(self.cursor, c);
-                // Return cursor movement: +1
-                (true, 1)
+                // Insert the character into captured text at cursor position.
+                // A combining character joins the previous grapheme.
+                let next = graphemes::insert(&mut self.text.borrow_mut(), self.cursor, &text);
+                let right = next - self.cursor;
+                self.cursor = next;
+                (true, right as i32)
             }
             // Remove the character to the left of cursor, then move cursor left
             KeyCode::Backspace => {
                 if self.cursor_left() {
-                    let remove = self.cursor;
-                    self.text.borrow_mut().remove(remove);
+                    graphemes::remove(&mut self.text.borrow_mut(), self.cursor);
                     (true, -1)
                 } else {
                     (true, 0)
@@ -105,8 +97,6 @@ impl TextCapture {
             // Move cursor to end of content
             KeyCode::End => {
                 let right = self
-                    .text
-                    .borrow()
                     .len()
                     .checked_sub(self.cursor)
                     .expect("off-by-one for end-key");
@@ -118,23 +108,19 @@ impl TextCapture {
             KeyCode::PageDown => (false, 0),
             // Move 4 or less to the right
             KeyCode::Tab => {
-                let right = 0.max(self.text.borrow().len() - self.cursor + 4);
+                let right = (self.len() - self.cursor).min(4);
                 self.cursor += right;
                 (true, right as i32)
             }
             // Move 4 or less to the left
             KeyCode::BackTab => {
-                let left = 0.min(self.cursor as i32 - 4);
-                self.cursor -= left as usize;
-                (true, left)
+                let left = self.cursor.min(4);
+                self.cursor -= left;
+                (true, -(left as i32))
             }
             // Remove the character right of cursor
             KeyCode::Delete => {
-                let line_len = self.text.borrow().len();
-                if self.cursor != line_len {
-                    let remove = self.cursor;
-                    self.text.borrow_mut().remove(remove);
-                }
+                graphemes::remove(&mut self.text.borrow_mut(), self.cursor);
                 (false, 0)
             }
             KeyCode::Insert => (false, 0),
@@ -172,7 +158,7 @@ impl TextCapture {
     }
 
     fn cursor_right(&mut self) -> bool {
-        if self.cursor >= self.text.borrow().len() {
+        if self.cursor >= self.len() {
             false
         } else {
             // Move cursor right
@@ -184,19 +170,28 @@ impl TextCapture {
     pub fn set_text(&mut self, text: String) {
         *self.text.borrow_mut() = text;
     }
-    pub fn text(&self) -> Ref<String> {
+    pub fn text(&self) -> Ref<'_, String> {
         self.text.borrow()
     }
+    /// Cursor position in graphemes
     pub fn cursor(&self) -> u16 {
         self.cursor as u16
     }
+    /// Columns the text left of the cursor takes on screen
+    pub fn cursor_x(&self) -> u16 {
+        graphemes::width_before(&self.text(), self.cursor) as u16
+    }
+    /// Length of the text in graphemes
+    fn len(&self) -> usize {
+        graphemes::count(&self.text())
+    }
 }
 
 impl Draw for TextCapture {
-    fn draw(&self, stdout: &mut Stdout) -> crossterm::Result<()> {
-        stdout.write_all(self.text().as_bytes())?;
+    fn draw(&self, out: &mut dyn Write) -> crossterm::Result<()> {
+        out.write_all(self.text().as_bytes())?;
 
-        stdout.flush()?;
+        out.flush()?;
         Ok(())
     }
 }
@@ -0,0 +1,165 @@
+use std::ops::Range;
+
+/// The part of the schedule visible on the terminal. Schedules taller than the
+/// terminal are scrolled so that the cursor stays visible. A separator row,
+/// the now line, may be drawn between two lines and takes a row of its own.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Viewport {
+    /// The terminal row where the schedule starts
+    pub y: u16,
+    /// Rows available for the schedule
+    pub height: u16,
+    /// Columns available, longer lines are cut
+    pub width: u16,
+    /// Index of the topmost visible line
+    pub offset: usize,
+    /// The line the separator row is drawn above, or the number of lines to
+    /// draw it below the last one
+    pub separator: Option<usize>,
+}
+
+impl Viewport {
+    /// Lines visible in a schedule of given length
+    pub fn visible(&self, len: usize) -> Range<usize> {
+        let lines = self.rows() - self.separator_row().is_some() as usize;
+        let start = self.offset.min(len);
+        let end = (self.offset + lines).min(len);
+        start..end
+    }
+
+    /// The terminal row of the separator, None if it is scrolled out of view
+    pub fn separator_row(&self) -> Option<u16> {
+        let idx = self.separator?.checked_sub(self.offset)?;
+        // A row is left for a line on a tiny terminal
+        if idx >= self.rows() || self.rows() < 2 {
+            return None;
+        }
+        Some(self.y + idx as u16)
+    }
+
+    /// Scrolls the least amount to make the line visible. Returns true if the
+    /// viewport scrolled.
+    pub fn scroll_to(&mut self, line: usize) -> bool {
+        let before = self.offset;
+        if line < self.offset {
+            self.offset = line;
+        } else if self.line_to_row(line).is_none() {
+            self.offset = line + 1 - self.rows();
+            // One more when the separator is in view above the line
+            if self.line_to_row(line).is_none() {
+                self.offset += 1;
+            }
+        }
+        self.offset != before
+    }
+
+    /// Scrolls back if the end of the schedule has moved up, e.g. after
+    /// removing lines, so that no rows are left empty needlessly
+    pub fn clamp(&mut self, len: usize) {
+        let mut end = Viewport {
+            offset: len.saturating_sub(self.rows()),
+            ..*self
+        };
+        if len > 0 {
+            end.scroll_to(len - 1);
+        }
+        self.offset = self.offset.min(end.offset);
+    }
+
+    /// Maps a terminal row to line index, None if the row is not on the
+    /// schedule
+    pub fn row_to_line(&self, row: u16) -> Option<usize> {
+        let mut idx = row.checked_sub(self.y)?;
+        if idx >= self.height.max(1) {
+            return None;
+        }
+        match self.separator_row() {
+            Some(separator) if row == separator => return None,
+            Some(separator) if row > separator => idx -= 1,
+            _ => {}
+        }
+        Some(self.offset + idx as usize)
+    }
+
+    /// Maps a line index to terminal row, None if the line is scrolled out of
+    /// view
+    pub fn line_to_row(&self, line: usize) -> Option<u16> {
+        let mut idx = line.checked_sub(self.offset)?;
+        if self.separator_row().is_some() && self.separator <= Some(line) {
+            idx += 1;
+        }
+        if idx >= self.rows() {
+            return None;
+        }
+        Some(self.y + idx as u16)
+    }
+
+    /// At least one row is always shown, even on a tiny terminal
+    fn rows(&self) -> usize {
+        self.height.max(1) as usize
+    }
+}
+
+#[test]
+fn viewport_follows_line() {
+    let mut viewport = Viewport {
+        y: 2,
+        height: 5,
+        width: 80,
+        offset: 0,
+        separator: None,
+    };
+
+    assert!(!viewport.scroll_to(4));
+    assert!(viewport.scroll_to(7));
+    assert_eq!(viewport.offset, 3);
+    assert_eq!(viewport.visible(40), 3..8);
+    assert_eq!(viewport.line_to_row(7), Some(6));
+    assert_eq!(viewport.row_to_line(2), Some(3));
+    assert_eq!(viewport.line_to_row(2), None);
+
+    assert!(viewport.scroll_to(1));
+    assert_eq!(viewport.offset, 1);
+
+    viewport.offset = 30;
+    viewport.clamp(32);
+    assert_eq!(viewport.offset, 27);
+}
+
+#[test]
+fn separator_takes_a_row() {
+    let mut viewport = Viewport {
+        y: 0,
+        height: 4,
+        width: 80,
+        offset: 0,
+        separator: Some(2),
+    };
+
+    // Lines 0 and 1, the separator, line 2
+    assert_eq!(viewport.visible(10), 0..3);
+    assert_eq!(viewport.separator_row(), Some(2));
+    assert_eq!(viewport.line_to_row(1), Some(1));
+    assert_eq!(viewport.line_to_row(2), Some(3));
+    assert_eq!(viewport.line_to_row(3), None);
+    assert_eq!(viewport.row_to_line(2), None);
+    assert_eq!(viewport.row_to_line(3), Some(2));
+
+    // Scrolling down to line 3 leaves room for the separator above it
+    assert!(viewport.scroll_to(3));
+    assert_eq!(viewport.offset, 1);
+    assert_eq!(viewport.line_to_row(3), Some(3));
+
+    // Out of view above, the separator takes no row
+    assert!(viewport.scroll_to(6));
+    assert_eq!(viewport.offset, 3);
+    assert_eq!(viewport.separator_row(), None);
+    assert_eq!(viewport.visible(10), 3..7);
+
+    // At the end, the last line stays at the bottom
+    viewport.separator = Some(8);
+    viewport.offset = 9;
+    viewport.clamp(10);
+    assert_eq!(viewport.offset, 7);
+    assert_eq!(viewport.line_to_row(9), Some(3));
+}
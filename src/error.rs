@@ -1,4 +1,4 @@
-use crate::editor;
+use crate::{editor, twilight};
 
 use thiserror::Error;
 
@@ -10,18 +10,8 @@ use std::io;
 pub enum Error {
     #[error("editor error")]
     Editor(#[from] editor::Error),
-    #[error("ureq error")]
-    Ureq(#[from] Box<ureq::Error>),
+    #[error("twilight error")]
+    Twilight(#[from] twilight::Error),
     #[error("I/O error")]
     Io(#[from] io::Error),
-    #[error("chrono parse error")]
-    ChronoParse(#[from] chrono::ParseError),
-    #[error("sunrise/sunset API error")]
-    SunriseApi(#[from] SunriseApiError),
-    #[error("serde JSON error")]
-    SerdeJson(#[from] serde_json::Error),
 }
-
-#[derive(Debug, Error)]
-#[error("sunrise/sunset API error {0}")]
-pub struct SunriseApiError(pub String);
@@ -1,22 +1,29 @@
 use crate::{
-    bind, bind_key,
+    bind, bind_key, bind_seq,
     editor::{
         command::{Binding, ColumnKind, Command, Dir, Filter, MoveCursor},
         Mode,
     },
+    multi,
 };
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
+const ALT_SHIFT: KeyModifiers =
+    KeyModifiers::from_bits_truncate(KeyModifiers::ALT.bits() | KeyModifiers::SHIFT.bits());
+
 /*
+    Default bindings, overridable in the config file (see editor::keymap).
+
     Modes:
     - Cursor
     - Insert
     - Time
-    - GoTo
-    - Delete
+    - Search
+    - Visual
 
     Globals:
     - ctrl + q: quit
+    - ctrl + s: save schedule
 
     Cursor mode:
     - h, l, left arrow, right arrow: move cursor (and ghost) horizontal
@@ -24,16 +31,60 @@ use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
     - i: insert mode
     - a: move right and insert mode
     - t: time mode
+    - V, v: visual mode, selecting lines from the one under the cursor
     - o: create line below and move in insert mode
     - O: create line above and move in insert mode
     - I: go to first column and insert
     - A: go to last column and insert
-    - 'g' modifier
-        - (NYI) 'g' move cursor to begin of file (first column)
-        - (NYI) 'G' move cursor to end of file (first column)
-    - 'd' modifier 'delete'
-        - d: delete line
-    - p: paste clipboard
+    - w, b, e: move to the next word, the previous word, the end of the word
+    - 0, ^, $: move to the first column, the first non-blank, the last character
+    - gg: move cursor to the first line, or to line [count]
+    - G: move cursor to the last line, or to line [count]
+    - gn: move cursor to the item at the current time
+    - C: catch up when running late, move the first item not done to now and
+      shift the rest
+    - alt + j, alt + k: move the item [count] lines down / up
+    - alt + J, alt + K: like above, but the times stay in place and only the
+      activities trade places
+    - dd: cut line, or [count] lines
+    - dj, dk: cut line and [count] lines below / above
+    - dt: delete time
+    - yy: copy line, or [count] lines
+    - yj, yk: copy line and [count] lines below / above
+    - ctrl + f, ctrl + b, page down, page up: move cursor a screenful down / up
+    - [count] before or in the middle of a sequence repeats it, e.g. 5j, d3j
+    - p, P: paste below / above
+    - "a before a cut, copy or paste uses register a instead of the unnamed
+      one, "A appends to it, "+ also copies to the system clipboard
+    - u: undo
+    - ctrl + r: redo
+    - /: search summaries for text, or for the item at a time like 14:30
+    - n, N: move to the next / previous match of the last search
+    - ':' command line
+        - w [file]: save, optionally into another file
+        - q: quit
+        - wq: save and quit, or stay if saving fails
+        - e file: open a saved schedule
+        - shift +0:30 [from 3]: shift times from cursor or from given line
+        - sort: sort items by time
+        - catchup: like C
+        - yank [register]: copy the whole schedule, e.g. yank + to paste the
+          day into another program
+        - set span 2:00: set the default length of new spans
+        - set gap 1:30: point out gaps between items longer than this
+        - kind meal: set the activity kind, meal, sprint or unknown
+
+    Visual mode:
+    - moving the cursor like in cursor mode extends the selection
+    - d, x: cut the selected lines
+    - y: copy the selected lines
+    - ctrl + a: toggle the selected items done, all done if some are not
+    - f: toggle fixed time adjust policy of the selected items
+    - >, <: shift the times of the selected items 15 minutes later / earlier
+    - D: delete the times of the selected items
+    - u, ctrl + r: undo, redo
+    - ':' command line, shift and kind act on the selected items
+    - Esc, V, v: cursor mode
 
     Time mode:
     - i: insert mode
@@ -43,15 +94,22 @@ use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
     - J, L: adjust time by 1 minute
     - r: toggle between relative and absolute time mode
     - f: toggle fixed time adjust policy
+    - u, ctrl + r: undo, redo
     - Esc: cursor mode
 
     Insert mode:
     - Esc: cursor mode
     - Arrow keys: move
+    - ctrl + left, ctrl + right: move to the previous / next word
+    - ctrl + w, ctrl + u: delete the word / everything before the cursor
+
+    Search mode:
+    - typing moves the cursor to the first match after where the search began
+    - Enter: keep the cursor at the match
+    - Esc: cancel and move the cursor back
 
     TODO:
 
-    - '/' search, on enter: move cursor to first match
     - 'ctrl + a' toggle item complete
 */
 
@@ -69,81 +127,201 @@ pub const BINDINGS: &[Binding] = &[
         Command::ToggleCrossOver,
         Filter::Global
     ),
+    bind!(
+        KeyCode::Char('s'),
+        KeyModifiers::CONTROL,
+        Command::Save,
+        Filter::Global
+    ),
     // Cursor mode
     bind_key!(
         'h',
         Command::MoveCursor(MoveCursor::Dir(Dir::Left)),
-        Filter::Mode(Mode::Cursor)
+        Filter::Modes(&[Mode::Cursor, Mode::Visual])
     ),
     bind_key!(
         'l',
         Command::MoveCursor(MoveCursor::Dir(Dir::Right)),
-        Filter::Mode(Mode::Cursor)
+        Filter::Modes(&[Mode::Cursor, Mode::Visual])
     ),
     bind_key!(
         'j',
         Command::MoveCursor(MoveCursor::Dir(Dir::Down)),
-        Filter::Mode(Mode::Cursor)
+        Filter::Modes(&[Mode::Cursor, Mode::Visual])
     ),
     bind_key!(
         'k',
         Command::MoveCursor(MoveCursor::Dir(Dir::Up)),
-        Filter::Mode(Mode::Cursor)
+        Filter::Modes(&[Mode::Cursor, Mode::Visual])
     ),
     bind_key!('i', Command::InsertMode, Filter::Mode(Mode::Cursor)),
     bind_key!(
         'a',
-        Command::Multi(&[
+        multi![
             Command::InsertMode,
             Command::MoveCursor(MoveCursor::Dir(Dir::Right))
-        ]),
+        ],
         Filter::Mode(Mode::Cursor)
     ),
     bind_key!('t', Command::TimeMode, Filter::Mode(Mode::Cursor)),
+    bind_key!('v', Command::VisualMode, Filter::Mode(Mode::Cursor)),
+    bind!(
+        KeyCode::Char('V'),
+        KeyModifiers::SHIFT,
+        Command::VisualMode,
+        Filter::Mode(Mode::Cursor)
+    ),
     bind_key!(
         'o',
-        Command::Multi(&[Command::InsertTimeBoxBelow, Command::InsertMode]),
+        multi![Command::InsertTimeBoxBelow, Command::InsertMode],
         Filter::Mode(Mode::Cursor)
     ),
     bind!(
         KeyCode::Char('O'),
         KeyModifiers::SHIFT,
-        Command::Multi(&[Command::InsertTimeBoxAbove, Command::InsertMode]),
+        multi![Command::InsertTimeBoxAbove, Command::InsertMode],
         Filter::Mode(Mode::Cursor)
     ),
     bind!(
         KeyCode::Char('I'),
         KeyModifiers::SHIFT,
-        Command::Multi(&[
+        multi![
             Command::GoToColumn(ColumnKind::Index(0)),
             Command::InsertMode
-        ]),
+        ],
         Filter::Mode(Mode::Cursor)
     ),
     bind!(
         KeyCode::Char('A'),
         KeyModifiers::SHIFT,
-        Command::Multi(&[Command::GoToColumn(ColumnKind::Last), Command::InsertMode]),
+        multi![Command::GoToColumn(ColumnKind::Last), Command::InsertMode],
         Filter::Mode(Mode::Cursor)
     ),
+    bind_key!(
+        'w',
+        Command::MoveCursor(MoveCursor::WordForward),
+        Filter::Modes(&[Mode::Cursor, Mode::Visual])
+    ),
+    bind_key!(
+        'b',
+        Command::MoveCursor(MoveCursor::WordBackward),
+        Filter::Modes(&[Mode::Cursor, Mode::Visual])
+    ),
+    bind_key!(
+        'e',
+        Command::MoveCursor(MoveCursor::WordEnd),
+        Filter::Modes(&[Mode::Cursor, Mode::Visual])
+    ),
+    bind_key!(
+        '0',
+        Command::MoveCursor(MoveCursor::LineStart),
+        Filter::Modes(&[Mode::Cursor, Mode::Visual])
+    ),
+    bind_key!(
+        '^',
+        Command::MoveCursor(MoveCursor::FirstNonBlank),
+        Filter::Modes(&[Mode::Cursor, Mode::Visual])
+    ),
+    bind_key!(
+        '$',
+        Command::MoveCursor(MoveCursor::LineEnd),
+        Filter::Modes(&[Mode::Cursor, Mode::Visual])
+    ),
+    bind_seq!(
+        ['g', 'g'],
+        Command::MoveCursor(MoveCursor::Top),
+        Filter::Modes(&[Mode::Cursor, Mode::Visual])
+    ),
     bind!(
-        KeyCode::Char('g'),
-        KeyModifiers::NONE,
-        Command::GoToMode,
+        KeyCode::Char('G'),
+        KeyModifiers::SHIFT,
+        Command::MoveCursor(MoveCursor::Bottom),
+        Filter::Modes(&[Mode::Cursor, Mode::Visual])
+    ),
+    bind_seq!(
+        ['g', 'n'],
+        Command::JumpToNow,
+        Filter::Modes(&[Mode::Cursor, Mode::Visual])
+    ),
+    bind!(
+        KeyCode::Char('C'),
+        KeyModifiers::SHIFT,
+        Command::CatchUp,
         Filter::Mode(Mode::Cursor)
     ),
     bind!(
-        KeyCode::Char('d'),
-        KeyModifiers::NONE,
-        Command::DeleteMode,
+        KeyCode::Char('j'),
+        KeyModifiers::ALT,
+        Command::MoveTimeBox {
+            dir: Dir::Down,
+            keep_times: false
+        },
+        Filter::Mode(Mode::Cursor)
+    ),
+    bind!(
+        KeyCode::Char('k'),
+        KeyModifiers::ALT,
+        Command::MoveTimeBox {
+            dir: Dir::Up,
+            keep_times: false
+        },
+        Filter::Mode(Mode::Cursor)
+    ),
+    bind!(
+        KeyCode::Char('J'),
+        ALT_SHIFT,
+        Command::MoveTimeBox {
+            dir: Dir::Down,
+            keep_times: true
+        },
+        Filter::Mode(Mode::Cursor)
+    ),
+    bind!(
+        KeyCode::Char('K'),
+        ALT_SHIFT,
+        Command::MoveTimeBox {
+            dir: Dir::Up,
+            keep_times: true
+        },
+        Filter::Mode(Mode::Cursor)
+    ),
+    bind_seq!(
+        ['d', 'd'],
+        Command::CutCurrentLine,
+        Filter::Mode(Mode::Cursor)
+    ),
+    bind_seq!(
+        ['d', 'j'],
+        Command::CutLines(Dir::Down),
+        Filter::Mode(Mode::Cursor)
+    ),
+    bind_seq!(
+        ['d', 'k'],
+        Command::CutLines(Dir::Up),
+        Filter::Mode(Mode::Cursor)
+    ),
+    bind_seq!(['d', 't'], Command::DeleteTime, Filter::Mode(Mode::Cursor)),
+    bind_seq!(
+        ['y', 'y'],
+        Command::YankCurrentLine,
+        Filter::Mode(Mode::Cursor)
+    ),
+    bind_seq!(
+        ['y', 'j'],
+        Command::YankLines(Dir::Down),
+        Filter::Mode(Mode::Cursor)
+    ),
+    bind_seq!(
+        ['y', 'k'],
+        Command::YankLines(Dir::Up),
         Filter::Mode(Mode::Cursor)
     ),
     bind_key!(
         'p',
-        Command::Multi(&[
+        multi![
             Command::PasteBelow,
             Command::MoveCursor(MoveCursor::Dir(Dir::Down))
-        ]),
+        ],
         Filter::Mode(Mode::Cursor)
     ),
     bind!(
@@ -151,13 +329,80 @@ pub const BINDINGS: &[Binding] = &[
         KeyModifiers::SHIFT,
         Command::PasteAbove,
         Filter::Mode(Mode::Cursor)
-    ), /*
-       bind!(
-           KeyCode::Char(':'),
-           KeyModifiers::NONE,
-           Command::OpenCommandInput,
-           Filter::Mode(Mode::Cursor)
-       ),*/
+    ),
+    bind_key!(
+        ':',
+        Command::OpenCommandInput,
+        Filter::Modes(&[Mode::Cursor, Mode::Visual])
+    ),
+    bind_key!('/', Command::Search, Filter::Mode(Mode::Cursor)),
+    bind_key!(
+        'n',
+        Command::SearchNext,
+        Filter::Modes(&[Mode::Cursor, Mode::Visual])
+    ),
+    bind!(
+        KeyCode::Char('N'),
+        KeyModifiers::SHIFT,
+        Command::SearchPrevious,
+        Filter::Modes(&[Mode::Cursor, Mode::Visual])
+    ),
+    bind!(
+        KeyCode::Char('f'),
+        KeyModifiers::CONTROL,
+        Command::MoveCursor(MoveCursor::PageDown),
+        Filter::Modes(&[Mode::Cursor, Mode::Visual])
+    ),
+    bind!(
+        KeyCode::Char('b'),
+        KeyModifiers::CONTROL,
+        Command::MoveCursor(MoveCursor::PageUp),
+        Filter::Modes(&[Mode::Cursor, Mode::Visual])
+    ),
+    // Visual-mode
+    bind!(
+        KeyCode::Esc,
+        KeyModifiers::NONE,
+        Command::CursorMode,
+        Filter::Mode(Mode::Visual)
+    ),
+    bind_key!('v', Command::CursorMode, Filter::Mode(Mode::Visual)),
+    bind!(
+        KeyCode::Char('V'),
+        KeyModifiers::SHIFT,
+        Command::CursorMode,
+        Filter::Mode(Mode::Visual)
+    ),
+    bind_key!('d', Command::CutCurrentLine, Filter::Mode(Mode::Visual)),
+    bind_key!('x', Command::CutCurrentLine, Filter::Mode(Mode::Visual)),
+    bind_key!('y', Command::YankCurrentLine, Filter::Mode(Mode::Visual)),
+    bind_key!(
+        'f',
+        Command::ToggleTimeAdjustPolicyFixed,
+        Filter::Mode(Mode::Visual)
+    ),
+    bind_key!(
+        '>',
+        Command::AdjustTime {
+            hours: 0,
+            minutes: 15
+        },
+        Filter::Mode(Mode::Visual)
+    ),
+    bind_key!(
+        '<',
+        Command::AdjustTime {
+            hours: 0,
+            minutes: -15
+        },
+        Filter::Mode(Mode::Visual)
+    ),
+    bind!(
+        KeyCode::Char('D'),
+        KeyModifiers::SHIFT,
+        Command::DeleteTime,
+        Filter::Mode(Mode::Visual)
+    ),
     // Time-mode
     bind_key!('i', Command::InsertMode, Filter::Mode(Mode::Time)),
     bind_key!('t', Command::MoveTimeCursor, Filter::Mode(Mode::Time)),
@@ -250,57 +495,95 @@ pub const BINDINGS: &[Binding] = &[
     bind!(
         KeyCode::Esc,
         KeyModifiers::NONE,
-        Command::Multi(&[
+        multi![
             Command::MoveCursor(MoveCursor::Dir(Dir::Left)),
             Command::CursorMode
-        ]),
+        ],
         Filter::Mode(Mode::Insert)
     ),
-    // GoTo mode
-    /*
     bind!(
-        KeyCode::Char('g'),
-        KeyModifiers::NONE,
-        Command::MoveCursor(MoveCursor::Top),
-        Filter::Mode(Mode::GoTo)
+        KeyCode::Left,
+        KeyModifiers::CONTROL,
+        Command::MoveCursor(MoveCursor::WordBackward),
+        Filter::Mode(Mode::Insert)
     ),
     bind!(
-        KeyCode::Char('G'),
-        KeyModifiers::SHIFT,
-        Command::MoveCursor(MoveCursor::Bottom),
-        Filter::Mode(Mode::GoTo)
-    ),*/
-    // Delete mode
+        KeyCode::Right,
+        KeyModifiers::CONTROL,
+        Command::MoveCursor(MoveCursor::WordForward),
+        Filter::Mode(Mode::Insert)
+    ),
+    bind!(
+        KeyCode::Char('w'),
+        KeyModifiers::CONTROL,
+        Command::DeleteTo(MoveCursor::WordBackward),
+        Filter::Mode(Mode::Insert)
+    ),
+    bind!(
+        KeyCode::Char('u'),
+        KeyModifiers::CONTROL,
+        Command::DeleteTo(MoveCursor::LineStart),
+        Filter::Mode(Mode::Insert)
+    ),
+    // Search-mode
     bind!(
-        KeyCode::Char('d'),
+        KeyCode::Esc,
         KeyModifiers::NONE,
-        Command::CutCurrentLine,
-        Filter::Mode(Mode::Delete)
+        Command::CancelSearch,
+        Filter::Mode(Mode::Search)
+    ),
+    bind!(
+        KeyCode::Enter,
+        KeyModifiers::NONE,
+        Command::ConfirmSearch,
+        Filter::Mode(Mode::Search)
     ),
-    bind_key!('t', Command::DeleteTime, Filter::Mode(Mode::Delete)),
     // Multiple modes
+    bind_key!(
+        'u',
+        Command::Undo,
+        Filter::Modes(&[Mode::Cursor, Mode::Time, Mode::Visual])
+    ),
+    bind!(
+        KeyCode::Char('r'),
+        KeyModifiers::CONTROL,
+        Command::Redo,
+        Filter::Modes(&[Mode::Cursor, Mode::Time, Mode::Visual])
+    ),
     bind!(
         KeyCode::Down,
         KeyModifiers::NONE,
         Command::MoveCursor(MoveCursor::Dir(Dir::Down)),
-        Filter::Modes(&[Mode::Insert, Mode::Cursor])
+        Filter::Modes(&[Mode::Insert, Mode::Cursor, Mode::Visual])
     ),
     bind!(
         KeyCode::Up,
         KeyModifiers::NONE,
         Command::MoveCursor(MoveCursor::Dir(Dir::Up)),
-        Filter::Modes(&[Mode::Insert, Mode::Cursor])
+        Filter::Modes(&[Mode::Insert, Mode::Cursor, Mode::Visual])
     ),
     bind!(
         KeyCode::Left,
         KeyModifiers::NONE,
         Command::MoveCursor(MoveCursor::Dir(Dir::Left)),
-        Filter::Modes(&[Mode::Insert, Mode::Cursor])
+        Filter::Modes(&[Mode::Insert, Mode::Cursor, Mode::Visual])
     ),
     bind!(
         KeyCode::Right,
         KeyModifiers::NONE,
         Command::MoveCursor(MoveCursor::Dir(Dir::Right)),
-        Filter::Modes(&[Mode::Insert, Mode::Cursor])
+        Filter::Modes(&[Mode::Insert, Mode::Cursor, Mode::Visual])
+    ),
+    bind!(
+        KeyCode::PageDown,
+        KeyModifiers::NONE,
+        Command::MoveCursor(MoveCursor::PageDown),
+        Filter::Modes(&[Mode::Insert, Mode::Cursor, Mode::Visual])
+    ),
+    bind!(
+        KeyCode::PageUp,
+        KeyModifiers::NONE,
+        Command::MoveCursor(MoveCursor::PageUp),
+        Filter::Modes(&[Mode::Insert, Mode::Cursor, Mode::Visual])
     ),
 ];
@@ -1,8 +1,11 @@
+pub mod config;
+pub mod diagnostics;
 pub mod dom;
 pub mod editor;
 pub mod error;
 pub mod keys;
 pub mod schedule;
+pub mod schedule_format;
 pub mod template_parsing;
 pub mod time;
 pub mod twilight;
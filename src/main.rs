@@ -1,10 +1,17 @@
+use chrono::{Datelike, NaiveTime};
 use clap::{App, Arg};
-use daily_planner::editor::{EditorLike, State};
+use daily_planner::config::{Config, TwilightSource};
+use daily_planner::editor::{EditorLike, Keymap, State};
+use daily_planner::schedule::Schedule;
 use daily_planner::template_parsing::{Template, TemplateMeta};
 use daily_planner::time::Duration;
 pub use daily_planner::time::{Clock, Time};
+use daily_planner::twilight::{
+    Cached, FixedTwilight, SolarCalculator, SunriseSunsetApi, TimeZone, Twilight, TwilightProvider,
+};
 use fs_err as fs;
 use std::io::stdout;
+use std::path::PathBuf;
 use std::str::FromStr;
 
 fn main() -> std::result::Result<(), daily_planner::editor::Error> {
@@ -18,49 +25,201 @@ fn main() -> std::result::Result<(), daily_planner::editor::Error> {
         .arg(Arg::from_usage(
             "-t --template=[FILE] 'Sets the schedule template.'",
         ))
+        .arg(
+            Arg::from_usage("--lat=[DEGREES] 'Sets the latitude used for sunrise and sunset.'")
+                .validator(is_degrees),
+        )
+        .arg(
+            Arg::from_usage("--lng=[DEGREES] 'Sets the longitude used for sunrise and sunset.'")
+                .validator(is_degrees),
+        )
+        .arg(
+            Arg::from_usage(
+                "--tz=[ZONE] 'Sets the time zone, e.g. Europe/Helsinki, +02:00 or local (default).'",
+            )
+            .validator(|tz| TimeZone::from_str(&tz).map(|_| ())),
+        )
+        .arg(
+            Arg::from_usage(
+                "--twilight=[SOURCE] 'Sets where sunrise and sunset come from: calculated offline (default), fetched online from api.sunrise-sunset.org or fixed by --sunrise and --sunset.'",
+            )
+            .possible_values(&["offline", "online", "fixed"]),
+        )
+        .arg(
+            Arg::from_usage("--sunrise=[TIME] 'Sets the time of sunrise for fixed twilight.'")
+                .validator(is_clock_time),
+        )
+        .arg(
+            Arg::from_usage("--sunset=[TIME] 'Sets the time of sunset for fixed twilight.'")
+                .validator(is_clock_time),
+        )
+        .arg(Arg::from_usage(
+            "-s --schedule=[FILE] 'Opens a previously saved schedule instead of creating one from the template.'",
+        ))
+        .arg(Arg::from_usage(
+            "-c --config=[FILE] 'Sets the config file. Defaults to config.toml in the daily-planner config directory.'",
+        ))
         .get_matches();
 
+    // Read the config, command line flags take precedence over it
+    let config = match matches.value_of("config") {
+        Some(file) => Some(PathBuf::from(file)),
+        None => Config::default_file(),
+    }
+    .map(|file| {
+        Config::load(&file).unwrap_or_else(|e| {
+            eprintln!("could not read config {}: {}", file.display(), e);
+            std::process::exit(1);
+        })
+    })
+    .unwrap_or_default();
+
+    // Fail early on invalid key bindings, before taking over the terminal
+    let keymap = Keymap::with_overrides(&config.keys).unwrap_or_else(|errors| {
+        for e in errors {
+            eprintln!("invalid key binding: {}", e);
+        }
+        std::process::exit(1);
+    });
+
+    let time_zone = match matches.value_of("tz") {
+        Some(tz) => TimeZone::from_str(tz).expect("validated by clap"),
+        None => config.time_zone.unwrap_or_default(),
+    };
+    let today = time_zone.today();
+
     // Determine time of wake up
-    let default_wake_up = Time::hm(9, 0);
+    let default_wake_up = config
+        .wake_up
+        .on(today.weekday())
+        .unwrap_or_else(|| Time::hm(9, 0));
     let wake_up_today = if let Some(wake_up) = matches.value_of("wake-up") {
         Time::from_str(wake_up).unwrap_or(default_wake_up)
     } else {
         default_wake_up
     }
     .round_to_half();
+    let default_wake_up_tomorrow = config
+        .wake_up
+        .on(today.succ().weekday())
+        .unwrap_or(wake_up_today);
     let wake_up_tomorrow = if let Some(wake_up_tomorrow) = matches.value_of("wake-up-tomorrow") {
-        Time::from_str(wake_up_tomorrow).unwrap_or(wake_up_today)
+        Time::from_str(wake_up_tomorrow).unwrap_or(default_wake_up_tomorrow)
     } else {
-        wake_up_today
+        default_wake_up_tomorrow
     }
     .round_to_half();
 
-    // Load template
-    let default_template_file = "data/template.md";
-    let template_file = match matches.value_of("template") {
-        Some(f) => f,
-        None => default_template_file,
+    // Determine location for sunrise and sunset
+    let mut location = config.location.unwrap_or_default();
+    if let Some(lat) = matches.value_of("lat") {
+        location.lat = lat.parse().expect("validated by clap");
+    }
+    if let Some(lng) = matches.value_of("lng") {
+        location.lng = lng.parse().expect("validated by clap");
+    }
+
+    // Load a saved schedule or create one from template
+    let span_len = config.span_len.unwrap_or_else(|| Duration::hm(3, 15));
+    let twilight_source = match matches.value_of("twilight") {
+        Some("online") => TwilightSource::Online,
+        Some("fixed") => TwilightSource::Fixed,
+        Some(_) => TwilightSource::Offline,
+        None => config.twilight.source,
+    };
+    let twilight_provider: Box<dyn TwilightProvider> = match twilight_source {
+        TwilightSource::Online => match dirs::cache_dir() {
+            Some(dir) => Box::new(Cached::new(SunriseSunsetApi, dir.join("daily-planner"))),
+            None => Box::new(SunriseSunsetApi),
+        },
+        TwilightSource::Fixed => {
+            let time_arg = |name, config_time: Option<Time>| match matches.value_of(name) {
+                Some(time) => {
+                    Some(NaiveTime::parse_from_str(time, "%H:%M").expect("validated by clap"))
+                }
+                None => config_time
+                    .map(|time| NaiveTime::from_hms(time.hour as u32, time.min as u32, 0)),
+            };
+            Box::new(FixedTwilight(Twilight {
+                sunrise: time_arg("sunrise", config.twilight.sunrise),
+                sunset: time_arg("sunset", config.twilight.sunset),
+                ..Default::default()
+            }))
+        }
+        TwilightSource::Offline => Box::new(SolarCalculator),
+    };
+    let twilight = twilight_provider
+        .twilight(today, &location, &time_zone)
+        .unwrap_or_else(|e| {
+            eprintln!("could not determine sunrise and sunset: {}", e);
+            Twilight::default()
+        });
+    // Today's schedule is opened again instead of starting over from the
+    // template, so that saving does not overwrite it
+    let todays_file = config.schedule_file(today);
+    let schedule_file = match matches.value_of("schedule") {
+        Some(file) => Some(PathBuf::from(file)),
+        None => Some(todays_file.clone()).filter(|file| file.exists()),
     };
-    let template_text = fs::read_to_string(template_file).expect("could not read file");
-    let template = Template::from_str(&template_text)?;
+    let (schedule, schedule_file) = if let Some(schedule_file) = schedule_file {
+        let schedule_text = fs::read_to_string(&schedule_file).expect("could not read file");
+        let mut schedule = Schedule::from_str(&schedule_text)?;
+        schedule.sunrise = twilight.sunrise;
+        schedule.sunset = twilight.sunset;
+        (schedule, schedule_file)
+    } else {
+        // Load template
+        let template_file = match matches.value_of("template") {
+            Some(f) => PathBuf::from(f),
+            None => config
+                .template
+                .clone()
+                .unwrap_or_else(|| PathBuf::from("data/template.md")),
+        };
+        let template_text = fs::read_to_string(template_file).expect("could not read file");
+        let template = Template::from_str(&template_text)?;
+
+        // Create schedule from template
+        let meta = TemplateMeta {
+            wake_up_today,
+            wake_up_tomorrow,
+            span_len,
+            sunrise: twilight.sunrise,
+            sunset: twilight.sunset,
+        };
 
-    // Create schedule from template
-    let sunrise_sunset = daily_planner::twilight::get_sunrise_sunset_online();
-    let meta = TemplateMeta {
-        wake_up_today,
-        wake_up_tomorrow,
-        span_len: Duration::hm(3, 15),
-        sunrise: sunrise_sunset.as_ref().ok().map(|x| x.0),
-        sunset: sunrise_sunset.ok().map(|x| x.1),
+        (template.schedule(meta), todays_file)
     };
-    let schedule = template.schedule(meta);
 
     // Create the editor
     let stdout = stdout();
     let mut editor = State::try_from_schedule(schedule, stdout)?;
+    editor.file = Some(schedule_file);
+    editor.span_len = span_len;
+    if let Some(max_gap) = config.max_gap {
+        editor.max_gap = max_gap;
+    }
+    editor.theme = config.theme;
+    editor.time_zone = time_zone;
+    editor.keymap = keymap;
 
     // Capture IO in main loop
     editor.attach();
 
     Ok(())
 }
+
+/// Validates a latitude or longitude given on the command line
+fn is_degrees(degrees: String) -> std::result::Result<(), String> {
+    degrees
+        .parse::<f64>()
+        .map(|_| ())
+        .map_err(|_| format!("'{}': expected degrees like 61.44", degrees))
+}
+
+/// Validates a time of day given on the command line
+fn is_clock_time(time: String) -> std::result::Result<(), String> {
+    NaiveTime::parse_from_str(&time, "%H:%M")
+        .map(|_| ())
+        .map_err(|_| format!("'{}': expected a time like 06:30", time))
+}
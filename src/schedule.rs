@@ -3,7 +3,7 @@ use chrono::NaiveTime;
 use crate::{dom::TimeBox, editor::cursor::ContentCursor, time::Time};
 
 /// Main data structure
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Schedule {
     pub timeboxes: Vec<TimeBox>,
     pub wake_up: Time,
@@ -25,4 +25,67 @@ impl Schedule {
     pub fn line(&self, idx: usize) -> Option<&TimeBox> {
         self.timeboxes.get(idx)
     }
+
+    /// The line of the item covering a time. A time without a span lasts until
+    /// the next timed item.
+    pub fn line_at(&self, time: &Time) -> Option<usize> {
+        let target = time.minutes_since(&self.wake_up);
+        let slots = self.timed_slots();
+        slots
+            .iter()
+            .enumerate()
+            .find(|(idx, (_, start, end))| {
+                let end = end
+                    .or_else(|| slots.get(idx + 1).map(|(_, next_start, _)| *next_start))
+                    .unwrap_or(24 * 60);
+                *start <= target && target < end
+            })
+            .map(|(_, (line, _, _))| *line)
+    }
+
+    /// The line of the first timed item starting after a time
+    pub fn line_after(&self, time: &Time) -> Option<usize> {
+        let target = time.minutes_since(&self.wake_up);
+        self.timed_slots()
+            .into_iter()
+            .find(|(_, start, _)| *start > target)
+            .map(|(line, _, _)| line)
+    }
+
+    /// The line of the last timed item starting at or before a time
+    pub fn line_before(&self, time: &Time) -> Option<usize> {
+        let target = time.minutes_since(&self.wake_up);
+        self.timed_slots()
+            .into_iter()
+            .rev()
+            .find(|(_, start, _)| *start <= target)
+            .map(|(line, _, _)| line)
+    }
+
+    /// The line the now line is drawn above: the one after the item that
+    /// started last, or the first timed item when none has started yet. None
+    /// without timed items.
+    pub fn now_separator(&self, now: &Time) -> Option<usize> {
+        self.line_before(now)
+            .map(|line| line + 1)
+            .or_else(|| self.line_after(now))
+    }
+
+    /// Lines of timed items with their start and end in minutes since
+    /// wake-up, so that items past midnight come last
+    fn timed_slots(&self) -> Vec<(usize, u16, Option<u16>)> {
+        let since_wake_up = |t: &Time| t.minutes_since(&self.wake_up);
+        self.timeboxes
+            .iter()
+            .enumerate()
+            .filter_map(|(line, time_box)| {
+                let slot = time_box.time.as_ref()?;
+                Some((
+                    line,
+                    since_wake_up(slot.start()),
+                    slot.end().map(since_wake_up),
+                ))
+            })
+            .collect()
+    }
 }
@@ -0,0 +1,45 @@
+//! Concrete, already-planned schedules stored as Markdown lists.
+//!
+//! The format uses the same list dialect as the templates, but every time is
+//! absolute and each item carries its done state and adjust policy, e.g.
+//!
+//! ```text
+//! <!-- wake-up: 09:00 -->
+//! <!-- wake-up-tomorrow: 09:30 -->
+//! - [x] 09:00 Breakfast
+//! - [ ] ! 12:00--13:00 meal: Lunch with the team
+//! - [ ] Untimed item
+//! ```
+use std::str::FromStr;
+
+use crate::dom::activity::ActivityKind;
+
+mod read;
+mod write;
+
+/// Marks a time box with `AdjustPolicy::Fixed`
+const FIXED_TOKEN: &str = "!";
+const DONE_TOKEN: &str = "[x]";
+const NOT_DONE_TOKEN: &str = "[ ]";
+/// Makes the rest of the line be read as the summary verbatim
+const ESCAPE_TOKEN: char = '\\';
+const WAKE_UP_KEY: &str = "wake-up";
+const WAKE_UP_TOMORROW_KEY: &str = "wake-up-tomorrow";
+
+/// Parses an activity kind token like "meal:", returning None for anything
+/// else.
+fn activity_kind(token: &str) -> Option<ActivityKind> {
+    token
+        .strip_suffix(':')
+        .and_then(|kind| ActivityKind::from_str(kind).ok())
+        .filter(|kind| *kind != ActivityKind::Unknown)
+}
+
+/// Returns true for tokens that the schedule parser reads as a time slot, e.g.
+/// "14:00", "14:00--15:00" or the template time "+0:15".
+fn is_time_like(token: &str) -> bool {
+    token.contains(':')
+        && token
+            .chars()
+            .all(|c| c.is_ascii_digit() || [':', '-', '+', '%', 'H', 'M'].contains(&c))
+}
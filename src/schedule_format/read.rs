@@ -0,0 +1,274 @@
+use std::str::FromStr;
+
+use crate::{
+    dom::{activity::ActivityKind, timebox::AdjustPolicy, Activity, TimeBox, TimeSlotKind},
+    schedule::Schedule,
+    template_parsing::ParseError,
+    time::Time,
+};
+
+use super::{
+    activity_kind, is_time_like, DONE_TOKEN, ESCAPE_TOKEN, FIXED_TOKEN, NOT_DONE_TOKEN,
+    WAKE_UP_KEY, WAKE_UP_TOMORROW_KEY,
+};
+
+const MARKDOWN_LIST_TOKENS: &[char] = &['-', '*'];
+const SPAN_SEP_TOKEN: &str = "--";
+const TIME_SEP_TOKEN: char = ':';
+
+/// A parse error and the byte offset on the line where it occurred
+type LineError = (usize, ParseError);
+
+impl FromStr for Schedule {
+    type Err = ParseError;
+
+    /// Parses a schedule written by `Schedule::to_markdown`. Unlike templates,
+    /// all times are taken as-is. Missing wake-up times default to the time of
+    /// the first timed item.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut wake_up = None;
+        let mut wake_up_tomorrow = None;
+        let mut timeboxes = Vec::new();
+
+        for (line_idx, line) in s.lines().enumerate() {
+            let located = |(offset, e): LineError| e.at(line_idx + 1, column(line, offset));
+
+            // Skip empty lines
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            if let Some((key, value)) = parse_comment(line) {
+                let value_offset = value.as_ptr() as usize - line.as_ptr() as usize;
+                let parse_value = || parse_time(value).map_err(|e| located((value_offset, e)));
+                match key {
+                    WAKE_UP_KEY => wake_up = Some(parse_value()?),
+                    WAKE_UP_TOMORROW_KEY => wake_up_tomorrow = Some(parse_value()?),
+                    // Unknown keys are comments
+                    _ => {}
+                }
+                continue;
+            }
+
+            // Each line is a time box
+            timeboxes.push(parse_time_box(line).map_err(located)?);
+        }
+
+        let wake_up = wake_up
+            .or_else(|| {
+                timeboxes
+                    .iter()
+                    .find_map(|time_box: &TimeBox| time_box.time.as_ref())
+                    .map(|time| match time {
+                        TimeSlotKind::Time(t) => *t,
+                        TimeSlotKind::Span(start, _) => *start,
+                    })
+            })
+            .unwrap_or_else(|| Time::hm(0, 0));
+
+        // The editor needs a line for the cursor to be on
+        if timeboxes.is_empty() {
+            timeboxes.push(TimeBox::default());
+        }
+
+        Ok(Schedule {
+            timeboxes,
+            wake_up,
+            wake_up_tomorrow: wake_up_tomorrow.unwrap_or(wake_up),
+            sunrise: None,
+            sunset: None,
+        })
+    }
+}
+
+/// 1-based character column of a byte offset on the line
+fn column(line: &str, offset: usize) -> usize {
+    line[..offset].chars().count() + 1
+}
+
+/// Parses an HTML comment like "<!-- wake-up: 09:00 -->" into a key and a value.
+fn parse_comment(line: &str) -> Option<(&str, &str)> {
+    let inner = line
+        .trim()
+        .strip_prefix("<!--")?
+        .strip_suffix("-->")?
+        .trim();
+
+    Some(match inner.find(':') {
+        Some(sep) => (inner[..sep].trim(), inner[sep + 1..].trim()),
+        None => (inner, ""),
+    })
+}
+
+impl FromStr for TimeBox {
+    type Err = ParseError;
+
+    /// Parse a concrete time box from a line like "- [x] ! 12:00--13:00 meal: Lunch".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_time_box(s).map_err(|(offset, e)| e.at(1, column(s, offset)))
+    }
+}
+
+fn parse_time_box(line: &str) -> Result<TimeBox, LineError> {
+    // Byte offset of a suffix of the line
+    let offset = |rest: &str| line.len() - rest.len();
+
+    let mut rest = line.trim_start();
+
+    // Skip the list token
+    if let Some(after_list) = rest.strip_prefix(MARKDOWN_LIST_TOKENS) {
+        if after_list.is_empty() || after_list.starts_with(char::is_whitespace) {
+            rest = after_list.trim_start();
+        }
+    }
+
+    let mut time_box = TimeBox::default();
+    if let Some(after) = rest.strip_prefix(NOT_DONE_TOKEN) {
+        rest = after;
+    } else if let Some(after) = rest
+        .strip_prefix(DONE_TOKEN)
+        .or_else(|| rest.strip_prefix("[X]"))
+    {
+        time_box.done = true;
+        rest = after;
+    }
+
+    let mut activity = Activity::default();
+    loop {
+        rest = rest.trim_start();
+        let token = match rest.split_whitespace().next() {
+            Some(token) => token,
+            None => break,
+        };
+
+        // Escaped, everything after the escape is content
+        if let Some(escaped) = rest.strip_prefix(ESCAPE_TOKEN) {
+            activity.summary = escaped.to_owned();
+            break;
+        }
+
+        if token == FIXED_TOKEN && time_box.adjust_policy != AdjustPolicy::Fixed {
+            time_box.adjust_policy = AdjustPolicy::Fixed;
+        } else if time_box.time.is_none() && is_time_like(token) {
+            let time = parse_time_slot(token).map_err(|e| (offset(rest), e))?;
+            time_box.time = Some(time);
+        } else if let Some(kind) =
+            activity_kind(token).filter(|_| activity.kind == ActivityKind::Unknown)
+        {
+            activity.kind = kind;
+        } else {
+            // If nothing else applies, the rest of the line is content
+            activity.summary = rest.to_owned();
+            break;
+        }
+
+        rest = &rest[token.len()..];
+    }
+
+    time_box.activity = activity;
+    Ok(time_box)
+}
+
+/// Parses a time slot like "14:00" or "14:00--15:30".
+fn parse_time_slot(s: &str) -> Result<TimeSlotKind, ParseError> {
+    Ok(match s.find(SPAN_SEP_TOKEN) {
+        Some(sep_pos) => TimeSlotKind::Span(
+            parse_time(&s[..sep_pos])?,
+            parse_time(&s[sep_pos + SPAN_SEP_TOKEN.len()..])?,
+        ),
+        None => TimeSlotKind::Time(parse_time(s)?),
+    })
+}
+
+/// Parses an absolute time like "14:00". Template times like "+0:15" or
+/// "%H:%M" are rejected.
+fn parse_time(s: &str) -> Result<Time, ParseError> {
+    if s.starts_with(&['+', '-', '%'][..]) || s.contains('%') {
+        return Err(ParseError::TemplateTime(s.to_owned()));
+    }
+
+    let (hours, minutes) = s
+        .split_once(TIME_SEP_TOKEN)
+        .ok_or_else(|| ParseError::CantParseTime(s.to_owned()))?;
+    let is_number =
+        |x: &str| !x.is_empty() && x.len() <= 2 && x.chars().all(|c| c.is_ascii_digit());
+    if !is_number(hours) || !is_number(minutes) {
+        return Err(ParseError::CantParseTime(s.to_owned()));
+    }
+
+    let hour = hours.parse::<u8>().expect("checked above");
+    let min = minutes.parse::<u8>().expect("checked above");
+    if hour >= 24 || min >= 60 {
+        return Err(ParseError::CantParseTime(s.to_owned()));
+    }
+
+    Ok(Time::hm(hour, min))
+}
+
+#[test]
+fn markdown_round_trip() {
+    let timeboxes = vec![
+        TimeBox {
+            time: Some(TimeSlotKind::Time(Time::hm(9, 0))),
+            activity: Activity {
+                summary: "Breakfast".to_owned(),
+                kind: ActivityKind::Meal,
+            },
+            done: true,
+            adjust_policy: AdjustPolicy::Normal,
+        },
+        TimeBox {
+            time: Some(TimeSlotKind::Span(Time::hm(23, 30), Time::hm(1, 15))),
+            activity: Activity {
+                summary: "12:00 is  not a time, ! nor [x]".to_owned(),
+                kind: ActivityKind::Sprint,
+            },
+            done: false,
+            adjust_policy: AdjustPolicy::Fixed,
+        },
+        TimeBox {
+            time: None,
+            activity: Activity {
+                summary: "  meal: \\ escaped".to_owned(),
+                kind: ActivityKind::Unknown,
+            },
+            done: false,
+            adjust_policy: AdjustPolicy::Fixed,
+        },
+        TimeBox::default(),
+    ];
+    let schedule = Schedule {
+        timeboxes,
+        wake_up: Time::hm(8, 30),
+        wake_up_tomorrow: Time::hm(7, 0),
+        sunrise: None,
+        sunset: None,
+    };
+
+    let text = schedule.to_markdown();
+    let parsed = Schedule::from_str(&text).unwrap();
+    assert_eq!(parsed, schedule);
+    assert_eq!(parsed.to_markdown(), text);
+}
+
+#[test]
+fn empty_schedule_has_a_line() {
+    let schedule = Schedule::from_str("<!-- wake-up: 09:00 -->\n").unwrap();
+    assert_eq!(schedule.timeboxes, vec![TimeBox::default()]);
+    assert_eq!(schedule.wake_up, Time::hm(9, 0));
+    assert_eq!(Schedule::from_str("").unwrap().timeboxes.len(), 1);
+}
+
+#[test]
+fn schedule_parse_error_location() {
+    let text = "<!-- wake-up: 09:00 -->\n- [x] 09:00 Breakfast\n\n- [ ] ! +0:15 Walk\n";
+    match Schedule::from_str(text) {
+        Err(ParseError::At { line, column, .. }) => assert_eq!((line, column), (4, 9)),
+        other => panic!("expected a located error, got {:?}", other),
+    }
+
+    match Schedule::from_str("<!-- wake-up: 25:00 -->") {
+        Err(ParseError::At { line, column, .. }) => assert_eq!((line, column), (1, 15)),
+        other => panic!("expected a located error, got {:?}", other),
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{
+    dom::{activity::ActivityKind, timebox::AdjustPolicy, TimeBox, TimeSlotKind},
+    schedule::Schedule,
+};
+
+use super::{
+    activity_kind, is_time_like, DONE_TOKEN, ESCAPE_TOKEN, FIXED_TOKEN, NOT_DONE_TOKEN,
+    WAKE_UP_KEY, WAKE_UP_TOMORROW_KEY,
+};
+
+impl Schedule {
+    /// Serializes the schedule into Markdown that parses back into an equal
+    /// schedule.
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!(
+            "<!-- {}: {} -->\n<!-- {}: {} -->\n",
+            WAKE_UP_KEY, self.wake_up, WAKE_UP_TOMORROW_KEY, self.wake_up_tomorrow
+        );
+
+        for time_box in &self.timeboxes {
+            out.push_str(&time_box.to_markdown());
+            out.push('\n');
+        }
+
+        out
+    }
+}
+
+impl TimeBox {
+    /// Serializes the time box into a single Markdown list item, e.g.
+    /// "- [ ] ! 12:00--13:00 meal: Lunch".
+    pub fn to_markdown(&self) -> String {
+        let mut tokens = vec![
+            "-".to_owned(),
+            if self.done {
+                DONE_TOKEN
+            } else {
+                NOT_DONE_TOKEN
+            }
+            .to_owned(),
+        ];
+
+        if self.adjust_policy == AdjustPolicy::Fixed {
+            tokens.push(FIXED_TOKEN.to_owned());
+        }
+
+        if let Some(time) = &self.time {
+            tokens.push(match time {
+                TimeSlotKind::Time(t) => t.to_string(),
+                TimeSlotKind::Span(start, end) => format!("{}--{}", start, end),
+            });
+        }
+
+        if self.activity.kind != ActivityKind::Unknown {
+            tokens.push(format!("{}:", self.activity.kind));
+        }
+
+        let summary = &self.activity.summary;
+        if !summary.is_empty() {
+            if needs_escape(summary) {
+                tokens.push(format!("{}{}", ESCAPE_TOKEN, summary));
+            } else {
+                tokens.push(summary.clone());
+            }
+        }
+
+        tokens.join(" ")
+    }
+}
+
+/// Returns true if the summary would not be read back as-is, i.e. it starts
+/// with whitespace or its first word would be mistaken for a marker, time or
+/// activity kind.
+fn needs_escape(summary: &str) -> bool {
+    if summary.starts_with(char::is_whitespace) || summary.starts_with(ESCAPE_TOKEN) {
+        return true;
+    }
+
+    let first_word = summary.split_whitespace().next().unwrap_or_default();
+    if first_word == FIXED_TOKEN || is_time_like(first_word) {
+        return true;
+    }
+
+    activity_kind(first_word).is_some()
+}
@@ -1,7 +1,6 @@
 mod time;
 mod timebox;
 
-
 use std::str::FromStr;
 use thiserror::Error;
 
@@ -20,8 +19,27 @@ pub enum ParseError {
     NoActivity,
     #[error("could not parse activity")]
     InvalidActivity(#[from] ActivityDeserializationError),
-    #[error("could not parse time")]
+    #[error("could not parse time '{0}'")]
     CantParseTime(String),
+    #[error("'{0}' is a template time, expected an absolute time like 14:00")]
+    TemplateTime(String),
+    #[error("line {line}, column {column}: {source}")]
+    At {
+        line: usize,
+        column: usize,
+        source: Box<ParseError>,
+    },
+}
+
+impl ParseError {
+    /// Locates the error at a 1-based line and column
+    pub fn at(self, line: usize, column: usize) -> ParseError {
+        ParseError::At {
+            line,
+            column,
+            source: Box::new(self),
+        }
+    }
 }
 
 impl FromStr for Template {
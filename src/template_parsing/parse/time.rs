@@ -23,13 +23,8 @@ impl FromStr for TimeTemplate {
             .next()
             .ok_or_else(|| ParseError::CantParseTime(s.to_owned()))?;
 
-        let relative_time;
         // Relative time
-        if ['+', '-'].contains(&hours_token.chars().next().expect("hours token empty")) {
-            relative_time = true;
-        } else {
-            relative_time = false;
-        }
+        let relative_time = hours_token.starts_with(&['+', '-'][..]);
 
         // TODO: more involved format detection logic
         let hours = if hours_token == "%H" {
@@ -58,7 +53,9 @@ impl FromStr for TimeTemplate {
                 }
             }
             (None, None) => TimeTemplate::TimeFormat,
-            (None, Some(_)) | (Some(_), None) => unimplemented!(),
+            (None, Some(_)) | (Some(_), None) => {
+                return Err(ParseError::CantParseTime(s.to_owned()))
+            }
         })
     }
 }
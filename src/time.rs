@@ -1,13 +1,16 @@
 mod math;
 
-use std::{cmp, fmt, str::FromStr};
+use chrono::Timelike;
+use serde::Deserialize;
+use std::{cmp, convert::TryFrom, fmt, str::FromStr};
 
-use crate::template_parsing::template::TimeTemplate;
+use crate::{template_parsing::template::TimeTemplate, twilight::TimeZone};
 
 /// Represents naive time. May be used relatively in the span of 24 hours
 /// starting from wake-up, e.g. if wake up was at 10:00, 8:30 could be later
 /// than 10:00.
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
 pub struct Time {
     pub hour: u8,
     pub min: u8,
@@ -18,6 +21,19 @@ impl Time {
         Time { hour, min }
     }
 
+    /// The current time in given time zone
+    pub fn now(tz: &TimeZone) -> Time {
+        let now = tz.now();
+        Time::hm(now.hour() as u8, now.minute() as u8)
+    }
+
+    /// Minutes from `start` until this time, wrapping past midnight, e.g.
+    /// 01:00 is 120 minutes since 23:00.
+    pub fn minutes_since(&self, start: &Time) -> u16 {
+        let minutes = |t: &Time| t.hour as i32 * 60 + t.min as i32;
+        (minutes(self) - minutes(start)).rem_euclid(24 * 60) as u16
+    }
+
     pub fn adjust(&mut self, duration: &Duration) {
         *self += duration;
     }
@@ -27,7 +43,7 @@ impl Time {
         match which_quarter {
             0 => {
                 if self.min > 45 {
-                    self.hour += 1;
+                    self.hour = (self.hour + 1) % 24;
                 }
                 self.min = 0;
             }
@@ -49,7 +65,7 @@ impl Time {
         match which_half {
             0 => {
                 if self.min > 30 {
-                    self.hour += 1;
+                    self.hour = (self.hour + 1) % 24;
                 }
                 self.min = 0;
             }
@@ -70,10 +86,14 @@ fn time_rounding() {
 
     let t3 = Time::hm(2, 43).round_to_quarter();
     assert_eq!(t3, Time::hm(2, 45));
+
+    // Rounding up past midnight wraps around
+    assert_eq!(Time::hm(23, 55).round_to_quarter(), Time::hm(0, 0));
 }
 
 /// Represents both positive and negative durations.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
 pub struct Duration(chrono::Duration);
 
 impl Duration {
@@ -85,6 +105,9 @@ impl Duration {
             hours as i64 * 60 + minutes as i64,
         ))
     }
+    pub fn num_minutes(&self) -> i64 {
+        self.0.num_minutes()
+    }
 }
 
 impl From<Duration> for Time {
@@ -136,6 +159,44 @@ impl FromStr for Time {
     }
 }
 
+impl FromStr for Duration {
+    type Err = String;
+
+    /// Parses a duration like "2:00", "+0:30" or "-1:15".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (sign, unsigned) = match s.strip_prefix('-') {
+            Some(rest) => (-1, rest),
+            None => (1, s.strip_prefix('+').unwrap_or(s)),
+        };
+
+        let invalid = || format!("'{}': expected a duration like 1:30", s);
+        let (hours, minutes) = unsigned.split_once(':').ok_or_else(invalid)?;
+        let hours = hours.parse::<u8>().map_err(|_| invalid())?;
+        let minutes = minutes.parse::<u8>().map_err(|_| invalid())?;
+        if hours > 23 || minutes > 59 {
+            return Err(invalid());
+        }
+
+        Ok(Duration::hm(sign * hours as i8, sign * minutes as i8))
+    }
+}
+
+impl TryFrom<String> for Time {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Time::from_str(&s)
+    }
+}
+
+impl TryFrom<String> for Duration {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Duration::from_str(&s)
+    }
+}
+
 pub struct Clock {
     hour: u8,
     min: u8,
@@ -21,7 +21,7 @@ impl Clock {
     }
 }
 
-impl<'a, 'b> Sub<&'b Time> for &'a Time {
+impl<'b> Sub<&'b Time> for &Time {
     type Output = Duration;
 
     /// Calculates the difference between two times, assuming differences less
@@ -43,7 +43,7 @@ impl<'a, 'b> Sub<&'b Time> for &'a Time {
     }
 }
 
-impl<'a, 'b> Add<&'b Duration> for &'a Time {
+impl<'b> Add<&'b Duration> for &Time {
     type Output = Time;
 
     fn add(self, rhs: &'b Duration) -> Self::Output {
@@ -56,7 +56,7 @@ impl<'a, 'b> Add<&'b Duration> for &'a Time {
     }
 }
 
-impl<'a, 'b> Sub<&'b Duration> for &'a Time {
+impl<'b> Sub<&'b Duration> for &Time {
     type Output = Time;
 
     fn sub(self, rhs: &'b Duration) -> Self::Output {
@@ -81,7 +81,7 @@ impl SubAssign<&Duration> for Time {
     }
 }
 
-impl<'a, 'b> Add<&'b Duration> for &'a Duration {
+impl<'b> Add<&'b Duration> for &Duration {
     type Output = Duration;
 
     fn add(self, rhs: &'b Duration) -> Self::Output {
@@ -89,7 +89,7 @@ impl<'a, 'b> Add<&'b Duration> for &'a Duration {
     }
 }
 
-impl<'a, 'b> Sub<&'b Duration> for &'a Duration {
+impl<'b> Sub<&'b Duration> for &Duration {
     type Output = Duration;
 
     fn sub(self, rhs: &'b Duration) -> Self::Output {
@@ -1,42 +1,171 @@
-use crate::{error::SunriseApiError, Error};
-use chrono::NaiveTime;
-use std::result;
-
-pub fn get_sunrise_sunset_online() -> result::Result<(NaiveTime, NaiveTime), Error> {
-    // HACK: dawn/sunset REST testing
-    let lat = "61.441443";
-    let lng = "23.8658000";
-    let today = chrono::Local::now().date().naive_local();
-    let body_json: String = ureq::get(&format!(
-        "https://api.sunrise-sunset.org/json?lat={}&lng={}&date={}",
-        lat, lng, today
-    ))
-    .call()
-    .map_err(Box::new)?
-    .into_string()?;
-
-    let data: serde_json::Value = serde_json::from_str(&body_json)?;
-    let results = data
-        .get("results")
-        .ok_or_else(|| SunriseApiError("'results' not contained in body".to_owned()))?;
-    let sunrise = chrono::NaiveTime::parse_from_str(
-        results
-            .get("sunrise")
-            .ok_or_else(|| {
-                SunriseApiError("'sunrise' not contained in results returned from API".to_owned())
-            })?
-            .as_str()
-            .ok_or_else(|| SunriseApiError("value of 'sunrise' was empty string".to_owned()))?,
-        "%I:%M:%S %p",
-    )? + chrono::Duration::hours(2);
-    let sunset_str = results
-        .get("sunset")
-        .ok_or_else(|| {
-            SunriseApiError("'sunset' not contained in results returned from API".to_owned())
-        })?
-        .as_str()
-        .ok_or_else(|| SunriseApiError("value of 'sunset' was empty string".to_owned()))?;
-    let sunset =
-        chrono::NaiveTime::parse_from_str(sunset_str, "%I:%M:%S %p")? + chrono::Duration::hours(2);
-    Ok((sunrise, sunset))
+mod cache;
+mod error;
+mod online;
+mod solar;
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{convert::TryFrom, fmt, result, str::FromStr};
+
+pub use cache::Cached;
+pub use error::{Error, SunriseApiError};
+pub use online::SunriseSunsetApi;
+pub use solar::SolarCalculator;
+
+/// Source of sunrise, sunset and twilight times.
+pub trait TwilightProvider {
+    /// Returns the twilight times of given date and location, in local time of
+    /// given time zone.
+    fn twilight(
+        &self,
+        date: NaiveDate,
+        location: &Location,
+        tz: &TimeZone,
+    ) -> result::Result<Twilight, Error>;
+}
+
+/// Times of sunrise, sunset and the twilights around them during a day. An
+/// event is None when it does not happen on that day, e.g. there is no sunset
+/// during the midnight sun.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Twilight {
+    /// Sun rises to 12° below the horizon
+    pub nautical_dawn: Option<NaiveTime>,
+    /// Sun rises to 6° below the horizon
+    pub civil_dawn: Option<NaiveTime>,
+    pub sunrise: Option<NaiveTime>,
+    pub sunset: Option<NaiveTime>,
+    /// Sun sets to 6° below the horizon
+    pub civil_dusk: Option<NaiveTime>,
+    /// Sun sets to 12° below the horizon
+    pub nautical_dusk: Option<NaiveTime>,
+}
+
+/// Returns the same, manually given twilight times for every day.
+#[derive(Clone, Copy, Debug)]
+pub struct FixedTwilight(pub Twilight);
+
+impl TwilightProvider for FixedTwilight {
+    fn twilight(
+        &self,
+        _date: NaiveDate,
+        _location: &Location,
+        _tz: &TimeZone,
+    ) -> result::Result<Twilight, Error> {
+        Ok(self.0)
+    }
+}
+
+/// Geographic coordinates in degrees, used to determine sunrise and sunset.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Location {
+    pub lat: f64,
+    pub lng: f64,
+}
+
+impl Default for Location {
+    /// Tampere, Finland
+    fn default() -> Self {
+        Location {
+            lat: 61.441443,
+            lng: 23.8658,
+        }
+    }
+}
+
+/// The time zone that sunrise and sunset are shown in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum TimeZone {
+    /// The time zone of the operating system
+    #[default]
+    Local,
+    /// A named time zone, e.g. Europe/Helsinki
+    Named(chrono_tz::Tz),
+    /// A fixed offset from UTC, e.g. +02:00
+    Fixed(FixedOffset),
+}
+
+impl TimeZone {
+    /// Converts an UTC date and time into the local time in this time zone.
+    /// Daylight saving time is taken into account for local and named time
+    /// zones.
+    pub fn local_time(&self, utc: &DateTime<Utc>) -> NaiveTime {
+        match self {
+            TimeZone::Local => utc.with_timezone(&chrono::Local).time(),
+            TimeZone::Named(tz) => utc.with_timezone(tz).time(),
+            TimeZone::Fixed(offset) => utc.with_timezone(offset).time(),
+        }
+    }
+
+    /// The current time of day in this time zone
+    pub fn now(&self) -> NaiveTime {
+        self.local_time(&Utc::now())
+    }
+
+    /// Today's date in this time zone
+    pub fn today(&self) -> NaiveDate {
+        let now = Utc::now();
+        match self {
+            TimeZone::Local => now.with_timezone(&chrono::Local).date().naive_local(),
+            TimeZone::Named(tz) => now.with_timezone(tz).date().naive_local(),
+            TimeZone::Fixed(offset) => now.with_timezone(offset).date().naive_local(),
+        }
+    }
+}
+
+impl FromStr for TimeZone {
+    type Err = String;
+
+    /// Parses "local", an IANA time zone name like "Europe/Helsinki" or an
+    /// offset like "+02:00".
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("local") {
+            return Ok(TimeZone::Local);
+        }
+
+        if s.starts_with(&['+', '-'][..]) {
+            // Parse the offset as part of a full date-time
+            return DateTime::parse_from_rfc3339(&format!("2000-01-01T00:00:00{}", s))
+                .map(|dt| TimeZone::Fixed(*dt.offset()))
+                .map_err(|_| format!("'{}': expected an UTC offset like +02:00", s));
+        }
+
+        chrono_tz::Tz::from_str(s).map(TimeZone::Named)
+    }
+}
+
+impl TryFrom<String> for TimeZone {
+    type Error = String;
+
+    fn try_from(s: String) -> result::Result<Self, Self::Error> {
+        TimeZone::from_str(&s)
+    }
+}
+
+impl fmt::Display for TimeZone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeZone::Local => f.write_str("local"),
+            TimeZone::Named(tz) => write!(f, "{}", tz.name()),
+            TimeZone::Fixed(offset) => write!(f, "{}", offset),
+        }
+    }
+}
+
+#[test]
+fn time_zone_conversion() {
+    use chrono::TimeZone as _;
+
+    let helsinki = TimeZone::from_str("Europe/Helsinki").unwrap();
+    let winter = Utc.ymd(2021, 1, 15).and_hms(7, 30, 0);
+    let summer = Utc.ymd(2021, 7, 15).and_hms(7, 30, 0);
+    assert_eq!(helsinki.local_time(&winter), NaiveTime::from_hms(9, 30, 0));
+    assert_eq!(helsinki.local_time(&summer), NaiveTime::from_hms(10, 30, 0));
+
+    let fixed = TimeZone::from_str("-03:30").unwrap();
+    assert_eq!(fixed.local_time(&summer), NaiveTime::from_hms(4, 0, 0));
+
+    assert!(TimeZone::from_str("Mars/Olympus_Mons").is_err());
 }
@@ -0,0 +1,123 @@
+use chrono::NaiveDate;
+use fs_err as fs;
+use std::{collections::HashMap, path::PathBuf, result};
+
+use super::{Error, Location, TimeZone, Twilight, TwilightProvider};
+
+const CACHE_FILE: &str = "twilight.json";
+
+/// Caches the twilight times of another provider on disk, keyed by date,
+/// location and time zone. Repeated lookups on the same day don't hit the
+/// inner provider.
+#[derive(Debug)]
+pub struct Cached<P> {
+    inner: P,
+    file: PathBuf,
+}
+
+impl<P> Cached<P>
+where
+    P: TwilightProvider,
+{
+    /// Creates a cache into given directory
+    pub fn new(inner: P, dir: PathBuf) -> Cached<P> {
+        Cached {
+            inner,
+            file: dir.join(CACHE_FILE),
+        }
+    }
+
+    fn read(&self) -> HashMap<String, Twilight> {
+        // A missing or corrupt cache is treated as empty
+        fs::read_to_string(&self.file)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    fn write(&self, entries: &HashMap<String, Twilight>) -> result::Result<(), Error> {
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.file, serde_json::to_string_pretty(entries)?)?;
+        Ok(())
+    }
+}
+
+impl<P> TwilightProvider for Cached<P>
+where
+    P: TwilightProvider,
+{
+    fn twilight(
+        &self,
+        date: NaiveDate,
+        location: &Location,
+        tz: &TimeZone,
+    ) -> result::Result<Twilight, Error> {
+        let key = format!("{} {:.4},{:.4} {}", date, location.lat, location.lng, tz);
+
+        let mut entries = self.read();
+        if let Some(twilight) = entries.get(&key) {
+            return Ok(*twilight);
+        }
+
+        let twilight = self.inner.twilight(date, location, tz)?;
+
+        // Forget the past days, keys start with the date
+        let date = date.to_string();
+        entries.retain(|key, _| key.as_str() >= date.as_str());
+        entries.insert(key, twilight);
+        // Not being able to cache is no reason to throw the times away
+        if let Err(e) = self.write(&entries) {
+            eprintln!("could not cache sunrise and sunset: {}", e);
+        }
+
+        Ok(twilight)
+    }
+}
+
+#[test]
+fn cache_skips_inner_provider() {
+    use std::cell::Cell;
+
+    struct Counting(Cell<usize>);
+    impl TwilightProvider for Counting {
+        fn twilight(
+            &self,
+            _date: NaiveDate,
+            _location: &Location,
+            _tz: &TimeZone,
+        ) -> result::Result<Twilight, Error> {
+            self.0.set(self.0.get() + 1);
+            Ok(Twilight {
+                sunrise: Some(chrono::NaiveTime::from_hms(6, 0, 0)),
+                ..Default::default()
+            })
+        }
+    }
+
+    let dir = std::env::temp_dir().join(format!("daily-planner-cache-{}", std::process::id()));
+    let cached = Cached::new(Counting(Cell::new(0)), dir.clone());
+    let date = NaiveDate::from_ymd(2021, 3, 1);
+
+    let first = cached.twilight(date, &Location::default(), &TimeZone::Local);
+    let second = cached.twilight(date, &Location::default(), &TimeZone::Local);
+    let other_place = Location { lat: 0.0, lng: 0.0 };
+    cached
+        .twilight(date, &other_place, &TimeZone::Local)
+        .unwrap();
+
+    // A cache that can not be written still gives the times
+    let blocker = dir.join("blocker");
+    fs::write(&blocker, "").unwrap();
+    let unwritable = Cached::new(Counting(Cell::new(0)), blocker.join("cache"));
+    let fetched = unwritable.twilight(date, &Location::default(), &TimeZone::Local);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(first.unwrap(), second.unwrap());
+    assert_eq!(cached.inner.0.get(), 2);
+    assert_eq!(
+        fetched.unwrap().sunrise,
+        Some(chrono::NaiveTime::from_hms(6, 0, 0))
+    );
+}
@@ -0,0 +1,22 @@
+use std::io;
+
+use thiserror::Error;
+
+/// Represents all errors that can happen while determining twilight times.
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("HTTP error: {0}")]
+    Http(#[from] Box<ureq::Error>),
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("chrono parse error: {0}")]
+    ChronoParse(#[from] chrono::ParseError),
+    #[error("serde JSON error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+    #[error("{0}")]
+    SunriseApi(#[from] SunriseApiError),
+}
+
+#[derive(Debug, Error)]
+#[error("sunrise/sunset API error {0}")]
+pub struct SunriseApiError(pub String);
@@ -0,0 +1,104 @@
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use std::result;
+
+use super::{Error, Location, SunriseApiError, TimeZone, Twilight, TwilightProvider};
+
+/// Unix time given by the API for an event that does not happen on the day
+const NOT_HAPPENING: i64 = 1;
+
+/// Fetches twilight times from api.sunrise-sunset.org. Requires network
+/// access and sends the location to a third party.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SunriseSunsetApi;
+
+impl TwilightProvider for SunriseSunsetApi {
+    fn twilight(
+        &self,
+        date: NaiveDate,
+        location: &Location,
+        tz: &TimeZone,
+    ) -> result::Result<Twilight, Error> {
+        get_twilight_online(date, location, tz)
+    }
+}
+
+fn get_twilight_online(
+    date: NaiveDate,
+    location: &Location,
+    tz: &TimeZone,
+) -> result::Result<Twilight, Error> {
+    // Request times as ISO 8601 in UTC
+    let body_json: String = ureq::get(&format!(
+        "https://api.sunrise-sunset.org/json?lat={}&lng={}&date={}&formatted=0",
+        location.lat, location.lng, date
+    ))
+    .call()
+    .map_err(Box::new)?
+    .into_string()?;
+
+    parse_twilight(&body_json, tz)
+}
+
+/// Reads the twilight times from a response of the API
+fn parse_twilight(body_json: &str, tz: &TimeZone) -> result::Result<Twilight, Error> {
+    let data: serde_json::Value = serde_json::from_str(body_json)?;
+    let results = data
+        .get("results")
+        .ok_or_else(|| SunriseApiError("'results' not contained in body".to_owned()))?;
+
+    let utc_time = |key: &str| -> result::Result<DateTime<Utc>, Error> {
+        let value = results
+            .get(key)
+            .ok_or_else(|| {
+                SunriseApiError(format!(
+                    "'{}' not contained in results returned from API",
+                    key
+                ))
+            })?
+            .as_str()
+            .ok_or_else(|| SunriseApiError(format!("value of '{}' was not a string", key)))?;
+        Ok(DateTime::parse_from_rfc3339(value)?.with_timezone(&Utc))
+    };
+
+    // Events that do not happen on the day are given as the first second of
+    // 1970
+    let local_time = |key: &str| -> result::Result<Option<NaiveTime>, Error> {
+        let utc = utc_time(key)?;
+        Ok(Some(utc)
+            .filter(|utc| utc.timestamp() != NOT_HAPPENING)
+            .map(|utc| tz.local_time(&utc)))
+    };
+
+    Ok(Twilight {
+        nautical_dawn: local_time("nautical_twilight_begin")?,
+        civil_dawn: local_time("civil_twilight_begin")?,
+        sunrise: local_time("sunrise")?,
+        sunset: local_time("sunset")?,
+        civil_dusk: local_time("civil_twilight_end")?,
+        nautical_dusk: local_time("nautical_twilight_end")?,
+    })
+}
+
+#[test]
+fn polar_events_do_not_happen() {
+    // Nautical dusk at Helsinki midsummer
+    let body = r#"{"results":{
+        "sunrise":"2021-06-21T00:54:00+00:00",
+        "sunset":"2021-06-21T19:50:00+00:00",
+        "solar_noon":"2021-06-21T10:22:00+00:00",
+        "day_length":68160,
+        "civil_twilight_begin":"2021-06-20T23:51:00+00:00",
+        "civil_twilight_end":"2021-06-21T20:53:00+00:00",
+        "nautical_twilight_begin":"1970-01-01T00:00:01+00:00",
+        "nautical_twilight_end":"1970-01-01T00:00:01+00:00",
+        "astronomical_twilight_begin":"1970-01-01T00:00:01+00:00",
+        "astronomical_twilight_end":"1970-01-01T00:00:01+00:00"
+    },"status":"OK"}"#;
+
+    let helsinki = TimeZone::Fixed(chrono::FixedOffset::east(3 * 3600));
+    let twilight = parse_twilight(body, &helsinki).unwrap();
+    assert_eq!(twilight.sunrise, Some(NaiveTime::from_hms(3, 54, 0)));
+    assert_eq!(twilight.civil_dawn, Some(NaiveTime::from_hms(2, 51, 0)));
+    assert_eq!(twilight.nautical_dawn, None);
+    assert_eq!(twilight.nautical_dusk, None);
+}
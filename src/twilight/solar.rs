@@ -0,0 +1,197 @@
+//! Offline sunrise and sunset calculation using the sunrise equation, see
+//! <https://en.wikipedia.org/wiki/Sunrise_equation>. Accurate to a minute or
+//! two outside the polar regions.
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use std::result;
+
+use super::{Error, Location, TimeZone, Twilight, TwilightProvider};
+
+/// Altitudes of the center of the sun in degrees. Sunrise and sunset account
+/// for atmospheric refraction and the radius of the sun.
+const SUNRISE_ALTITUDE: f64 = -0.833;
+const CIVIL_ALTITUDE: f64 = -6.0;
+const NAUTICAL_ALTITUDE: f64 = -12.0;
+
+/// Julian date of 2000-01-01 12:00 UTC
+const J2000: f64 = 2451545.0;
+/// Julian date of 1970-01-01 00:00 UTC
+const UNIX_EPOCH: f64 = 2440587.5;
+/// Obliquity of the ecliptic in degrees
+const EARTH_TILT: f64 = 23.4397;
+
+/// Calculates twilight times from the position of the sun, without network
+/// access.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SolarCalculator;
+
+impl TwilightProvider for SolarCalculator {
+    fn twilight(
+        &self,
+        date: NaiveDate,
+        location: &Location,
+        tz: &TimeZone,
+    ) -> result::Result<Twilight, Error> {
+        let day = SolarDay::new(date, location);
+        let local_time = |julian_date: Option<f64>| {
+            julian_date.map(|julian_date| tz.local_time(&julian_to_utc(julian_date)))
+        };
+
+        let (nautical_dawn, nautical_dusk) = day.crossings(NAUTICAL_ALTITUDE);
+        let (civil_dawn, civil_dusk) = day.crossings(CIVIL_ALTITUDE);
+        let (sunrise, sunset) = day.crossings(SUNRISE_ALTITUDE);
+
+        Ok(Twilight {
+            nautical_dawn: local_time(nautical_dawn),
+            civil_dawn: local_time(civil_dawn),
+            sunrise: local_time(sunrise),
+            sunset: local_time(sunset),
+            civil_dusk: local_time(civil_dusk),
+            nautical_dusk: local_time(nautical_dusk),
+        })
+    }
+}
+
+/// Position of the sun at solar noon of a day at a location
+struct SolarDay {
+    /// Julian date of solar noon
+    transit: f64,
+    /// Declination of the sun in radians
+    declination: f64,
+    /// Latitude of the observer in radians
+    latitude: f64,
+}
+
+impl SolarDay {
+    fn new(date: NaiveDate, location: &Location) -> SolarDay {
+        let days_since_j2000 = date
+            .signed_duration_since(NaiveDate::from_ymd(2000, 1, 1))
+            .num_days() as f64;
+        let mean_solar_time = days_since_j2000 - location.lng / 360.0;
+
+        let mean_anomaly = (357.5291 + 0.98560028 * mean_solar_time)
+            .rem_euclid(360.0)
+            .to_radians();
+        let center = 1.9148 * mean_anomaly.sin()
+            + 0.0200 * (2.0 * mean_anomaly).sin()
+            + 0.0003 * (3.0 * mean_anomaly).sin();
+        let ecliptic_longitude = (mean_anomaly.to_degrees() + center + 180.0 + 102.9372)
+            .rem_euclid(360.0)
+            .to_radians();
+
+        let transit = J2000 + mean_solar_time + 0.0053 * mean_anomaly.sin()
+            - 0.0069 * (2.0 * ecliptic_longitude).sin();
+        let declination = (ecliptic_longitude.sin() * EARTH_TILT.to_radians().sin()).asin();
+
+        SolarDay {
+            transit,
+            declination,
+            latitude: location.lat.to_radians(),
+        }
+    }
+
+    /// Julian dates of when the sun rises above and sets below given altitude.
+    /// None if the sun stays above or below the altitude for the whole day.
+    fn crossings(&self, altitude: f64) -> (Option<f64>, Option<f64>) {
+        let cos_hour_angle = (altitude.to_radians().sin()
+            - self.latitude.sin() * self.declination.sin())
+            / (self.latitude.cos() * self.declination.cos());
+        if !(-1.0..=1.0).contains(&cos_hour_angle) {
+            return (None, None);
+        }
+
+        let hour_angle = cos_hour_angle.acos().to_degrees();
+        (
+            Some(self.transit - hour_angle / 360.0),
+            Some(self.transit + hour_angle / 360.0),
+        )
+    }
+}
+
+fn julian_to_utc(julian_date: f64) -> DateTime<Utc> {
+    let seconds = ((julian_date - UNIX_EPOCH) * 86400.0).round() as i64;
+    DateTime::from_utc(NaiveDateTime::from_timestamp(seconds, 0), Utc)
+}
+
+#[test]
+fn solar_calculator_matches_tables() {
+    use chrono::NaiveTime;
+    use std::str::FromStr;
+
+    // Published sunrise and sunset times in local time
+    #[rustfmt::skip]
+    let tables = [
+        ("London, summer solstice", (51.5074, -0.1278), "Europe/London", (2021, 6, 21), (4, 43), (21, 21)),
+        ("London, winter solstice", (51.5074, -0.1278), "Europe/London", (2021, 12, 21), (8, 4), (15, 53)),
+        ("New York", (40.7128, -74.006), "America/New_York", (2021, 6, 20), (5, 25), (20, 31)),
+        ("Sydney", (-33.8688, 151.2093), "Australia/Sydney", (2021, 12, 21), (5, 40), (20, 5)),
+        ("Helsinki", (60.1699, 24.9384), "Europe/Helsinki", (2021, 6, 21), (3, 54), (22, 50)),
+    ];
+
+    let minutes = |t: NaiveTime| {
+        t.signed_duration_since(NaiveTime::from_hms(0, 0, 0))
+            .num_minutes()
+    };
+    for (place, (lat, lng), tz, (y, m, d), sunrise, sunset) in tables.iter() {
+        let tz = TimeZone::from_str(tz).unwrap();
+        let twilight = SolarCalculator
+            .twilight(
+                NaiveDate::from_ymd(*y, *m, *d),
+                &Location {
+                    lat: *lat,
+                    lng: *lng,
+                },
+                &tz,
+            )
+            .unwrap();
+
+        for (event, expected) in [(twilight.sunrise, sunrise), (twilight.sunset, sunset)].iter() {
+            let expected = NaiveTime::from_hms(expected.0, expected.1, 0);
+            let diff = minutes(event.expect("sun must rise and set")) - minutes(expected);
+            assert!(
+                diff.abs() <= 2,
+                "{}: {:?} is not {}",
+                place,
+                event,
+                expected
+            );
+        }
+
+        // Civil twilight is less than two hours before sunrise and after
+        // sunset, possibly on the other side of midnight
+        let within_two_hours = |from: Option<NaiveTime>, to: Option<NaiveTime>| {
+            let diff = (minutes(to.unwrap()) - minutes(from.unwrap())).rem_euclid(24 * 60);
+            diff > 0 && diff < 120
+        };
+        assert!(within_two_hours(twilight.civil_dawn, twilight.sunrise));
+        assert!(within_two_hours(twilight.sunset, twilight.civil_dusk));
+    }
+
+    // Nautical twilight lasts through the night in Helsinki at midsummer
+    let helsinki = Location {
+        lat: 60.1699,
+        lng: 24.9384,
+    };
+    let midsummer = SolarCalculator
+        .twilight(
+            NaiveDate::from_ymd(2021, 6, 21),
+            &helsinki,
+            &TimeZone::Local,
+        )
+        .unwrap();
+    assert_eq!(midsummer.nautical_dusk, None);
+
+    // Midnight sun and polar night in Tromsø
+    let tromso = Location {
+        lat: 69.6492,
+        lng: 18.9553,
+    };
+    let summer = SolarCalculator
+        .twilight(NaiveDate::from_ymd(2021, 6, 21), &tromso, &TimeZone::Local)
+        .unwrap();
+    assert_eq!((summer.sunrise, summer.sunset), (None, None));
+    let winter = SolarCalculator
+        .twilight(NaiveDate::from_ymd(2021, 12, 21), &tromso, &TimeZone::Local)
+        .unwrap();
+    assert_eq!((winter.sunrise, winter.sunset), (None, None));
+    assert!(winter.civil_dawn.is_some());
+}
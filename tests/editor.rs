@@ -0,0 +1,482 @@
+use std::str::FromStr;
+
+use crossterm::event::Event;
+use daily_planner::{
+    dom::{activity::ActivityKind, timebox::AdjustPolicy, TimeSlotKind},
+    editor::{Mode, ScriptedEvents, State},
+    schedule::Schedule,
+    time::{Duration, Time},
+    twilight::TimeZone,
+};
+
+const SCHEDULE: &str = "- [ ] 09:00 Work\n- [ ] 12:00--13:00 Lunch\n- [ ] 14:00 Walk\n";
+
+/// Runs the editor on a 40x10 screen until the keys run out, late in the
+/// evening after the items of the tests
+fn run(schedule: &str, script: &str) -> State<Vec<u8>, ScriptedEvents> {
+    run_at(schedule, script, |_| Time::hm(22, 0))
+}
+
+/// Like `run`, with the clock stopped at now
+fn run_at(
+    schedule: &str,
+    script: &str,
+    now: fn(&TimeZone) -> Time,
+) -> State<Vec<u8>, ScriptedEvents> {
+    let schedule = Schedule::from_str(schedule).unwrap();
+    let events = ScriptedEvents::from_script(script).unwrap();
+    let mut state = State::new(vec![], events, (40, 10), schedule);
+    state.clock = now;
+    state.run().unwrap();
+    state
+}
+
+fn summaries(state: &State<Vec<u8>, ScriptedEvents>) -> Vec<&str> {
+    state
+        .schedule
+        .timeboxes
+        .iter()
+        .map(|time_box| time_box.activity.summary.as_str())
+        .collect()
+}
+
+fn start(state: &State<Vec<u8>, ScriptedEvents>, line: usize) -> Option<Time> {
+    match state.schedule.timeboxes[line].time {
+        Some(TimeSlotKind::Time(t)) | Some(TimeSlotKind::Span(t, _)) => Some(t),
+        None => None,
+    }
+}
+
+#[test]
+fn insert_lines_and_text() {
+    let state = run(SCHEDULE, "oCoffee<Esc>ggOWake up<Esc>GA!<Esc>");
+
+    assert_eq!(
+        summaries(&state),
+        vec!["Wake up", "Work", "Coffee", "Lunch", "Walk!"]
+    );
+    assert_eq!(*state.mode.borrow(), Mode::Cursor);
+
+    let screen = state.screen();
+    assert_eq!(screen[0], "                  Wake up");
+    assert_eq!(screen[2], "                  Coffee");
+    assert_eq!(screen[4], "14:00             Walk!");
+}
+
+#[test]
+fn insert_mode_editing_keys() {
+    let state = run(SCHEDULE, "jAs<BS><BS>h<Left><Left>n<Esc>");
+
+    assert_eq!(summaries(&state)[1], "Lunnch");
+    let pos = state.cursor_pos().unwrap();
+    assert_eq!((pos.col, pos.line), (3, 1));
+}
+
+#[test]
+fn cut_and_paste_lines() {
+    let state = run(SCHEDULE, "ddp");
+    assert_eq!(summaries(&state), vec!["Lunch", "Work", "Walk"]);
+
+    let state = run(SCHEDULE, "jdjggP");
+    assert_eq!(summaries(&state), vec!["Lunch", "Walk", "Work"]);
+    assert_eq!(state.screen()[0], "12:00--13:00 (1h) Lunch");
+
+    // Undo brings back what was cut
+    let state = run(SCHEDULE, "2ddu");
+    assert_eq!(summaries(&state), vec!["Work", "Lunch", "Walk"]);
+}
+
+#[test]
+fn adjust_time() {
+    // Relative mode shifts the items below too
+    let state = run(SCHEDULE, "jtjjl<Esc>");
+    assert_eq!(start(&state, 0), Some(Time::hm(9, 0)));
+    assert_eq!(start(&state, 1), Some(Time::hm(13, 30)));
+    assert_eq!(start(&state, 2), Some(Time::hm(15, 30)));
+
+    // An item without time inherits one and is adjusted alone
+    let state = run(SCHEDULE, "oCoffee<Esc>tjj<Esc>");
+    assert_eq!(start(&state, 1), Some(Time::hm(9, 30)));
+    assert_eq!(start(&state, 2), Some(Time::hm(12, 0)));
+    assert_eq!(state.screen()[1], "09:30             Coffee");
+}
+
+#[test]
+fn switch_modes() {
+    let mut state = run(SCHEDULE, "t");
+    assert_eq!(*state.mode.borrow(), Mode::Time);
+    assert_eq!(state.screen()[9], "-- ADJUST TIME (relative) --");
+
+    state.events_mut().push_script("<Esc>").unwrap();
+    state.run().unwrap();
+    assert_eq!(*state.mode.borrow(), Mode::Cursor);
+    assert_eq!(state.screen()[9], "");
+
+    state.events_mut().push_script("ji").unwrap();
+    state.run().unwrap();
+    assert_eq!(*state.mode.borrow(), Mode::Insert);
+    assert_eq!(state.screen()[9], "-- INSERT --");
+    assert_eq!(state.cursor_pos().unwrap().line, 1);
+}
+
+#[test]
+fn command_line() {
+    let state = run(SCHEDULE, ":shift +0:30 from 2<CR>");
+    assert_eq!(start(&state, 0), Some(Time::hm(9, 0)));
+    assert_eq!(start(&state, 1), Some(Time::hm(12, 30)));
+    assert_eq!(start(&state, 2), Some(Time::hm(14, 30)));
+
+    // Esc cancels the command
+    let state = run(SCHEDULE, ":shift +1:00<Esc>");
+    assert_eq!(start(&state, 0), Some(Time::hm(9, 0)));
+
+    let state = run(SCHEDULE, ":nope<CR>");
+    assert_eq!(state.screen()[9], "not an editor command: nope");
+}
+
+#[test]
+fn unicode_editing() {
+    // Finnish, a wide CJK character and an emoji sequence
+    let state = run("- [ ] 09:00 Kävely\n", "Aä 漢👨‍👩‍👧<BS><Left><Left>x<Esc>");
+    assert_eq!(summaries(&state), vec!["Kävelyäx 漢"]);
+    assert_eq!(state.screen()[0], "09:00 Kävelyäx 漢");
+    let pos = state.cursor_pos().unwrap();
+    assert_eq!((pos.col, pos.line), (7, 0));
+
+    // Backspace and delete remove whole wide characters
+    let state = run(
+        "- [ ] 漢字 kanji\n",
+        "A<BS><BS><BS><BS><BS><BS><BS>x<Left><Left><Del><Esc>",
+    );
+    assert_eq!(summaries(&state), vec!["x"]);
+}
+
+#[test]
+fn word_and_line_motions() {
+    let schedule = "- [ ] 09:00 Walk the dog\n- [ ] Call mom\n";
+    let col = |script: &str| {
+        let pos = run(schedule, script).cursor_pos().unwrap();
+        (pos.col, pos.line)
+    };
+
+    assert_eq!(col("w"), (5, 0));
+    assert_eq!(col("2w"), (9, 0));
+    assert_eq!(col("3w"), (0, 1));
+    assert_eq!(col("e"), (3, 0));
+    assert_eq!(col("$b"), (9, 0));
+    assert_eq!(col("jb"), (9, 0));
+    assert_eq!(col("$0"), (0, 0));
+    assert_eq!(col("jI  <Esc>0^"), (2, 1));
+    assert_eq!(col("Gw$gg"), (7, 0));
+
+    // Vertical moves keep the column on screen across shorter lines
+    assert_eq!(col("$jk"), (11, 0));
+
+    // Insert mode word jumps and deletion
+    let state = run(schedule, "A<C-Left><C-w>x<Esc>");
+    assert_eq!(summaries(&state)[0], "Walk xdog");
+    let state = run(schedule, "A<C-Left><C-u><Esc>");
+    assert_eq!(summaries(&state)[0], "dog");
+    let state = run(schedule, "jA<C-u><Esc>");
+    assert_eq!(summaries(&state)[1], "");
+}
+
+#[test]
+fn search() {
+    let schedule = "- [ ] 09:00 Work\n- [ ] 12:00--13:00 Lunch at work\n- [ ] 14:00 Walk\n";
+    let pos = |state: &State<Vec<u8>, ScriptedEvents>| {
+        let pos = state.cursor_pos().unwrap();
+        (pos.col, pos.line)
+    };
+
+    let state = run(schedule, "/work<CR>");
+    assert_eq!(pos(&state), (9, 1));
+    assert_eq!(*state.mode.borrow(), Mode::Cursor);
+    assert_eq!(pos(&run(schedule, "/work<CR>n")), (0, 0));
+    assert_eq!(pos(&run(schedule, "/work<CR>N")), (0, 0));
+    assert_eq!(pos(&run(schedule, "/work<CR>nN")), (9, 1));
+
+    // A time jumps to the item covering it
+    assert_eq!(pos(&run(schedule, "/14:<CR>")), (0, 2));
+    assert_eq!(pos(&run(schedule, "/12:30<CR>")), (0, 1));
+
+    // The search is typed on the status bar, and Esc moves back
+    let mut state = run(schedule, "j/wal");
+    assert_eq!(*state.mode.borrow(), Mode::Search);
+    assert_eq!(state.screen()[9], "/wal");
+    assert_eq!(pos(&state), (0, 2));
+    state.events_mut().push_script("<Esc>").unwrap();
+    state.run().unwrap();
+    assert_eq!(pos(&state), (0, 1));
+    assert_eq!(state.screen()[9], "");
+
+    let state = run(schedule, "/nope<CR>");
+    assert_eq!(state.screen()[9], "pattern not found: nope");
+    let state = run(schedule, "n");
+    assert_eq!(state.screen()[9], "no previous search");
+}
+
+#[test]
+fn jump_to_now() {
+    let line_at =
+        |now: fn(&TimeZone) -> Time| run_at(SCHEDULE, "gn", now).cursor_pos().unwrap().line;
+
+    assert_eq!(line_at(|_| Time::hm(12, 30)), 1);
+    // Between items the next one is taken
+    assert_eq!(line_at(|_| Time::hm(13, 30)), 2);
+    assert_eq!(line_at(|_| Time::hm(10, 0)), 0);
+}
+
+#[test]
+fn catch_up() {
+    let schedule =
+        "- [x] 08:00 Coffee\n- [ ] 09:00 Work\n- [ ] ! 12:00--13:00 Lunch\n- [ ] 14:00 Walk\n";
+
+    // Now is rounded to a quarter
+    let state = run_at(schedule, "C", |_| Time::hm(10, 37));
+    assert_eq!(start(&state, 0), Some(Time::hm(8, 0)));
+    assert_eq!(start(&state, 1), Some(Time::hm(10, 30)));
+    assert_eq!(start(&state, 2), Some(Time::hm(12, 0)));
+    assert_eq!(start(&state, 3), Some(Time::hm(15, 30)));
+    assert_eq!(state.cursor_pos().unwrap().line, 1);
+    // Lunch ends long before the walk now
+    assert_eq!(state.screen()[9], "shifted 1h30m later               1 gap");
+
+    // On a wider screen, to fit the fixed items in the message
+    let events = ScriptedEvents::from_script(":catchup<CR>").unwrap();
+    let mut state = State::new(
+        vec![],
+        events,
+        (80, 10),
+        Schedule::from_str(schedule).unwrap(),
+    );
+    state.clock = |_| Time::hm(12, 30);
+    state.run().unwrap();
+    assert_eq!(
+        state.screen()[9],
+        "shifted 3h30m later, overlapping fixed: Lunch             1 out of order, 1 gap"
+    );
+
+    // Being early moves nothing
+    let state = run_at(schedule, "C", |_| Time::hm(8, 30));
+    assert_eq!(start(&state, 1), Some(Time::hm(9, 0)));
+    assert_eq!(state.screen()[9], "30m ahead of schedule");
+
+    // Undone in one step
+    let state = run_at(schedule, "Cu", |_| Time::hm(12, 30));
+    assert_eq!(start(&state, 1), Some(Time::hm(9, 0)));
+}
+
+#[test]
+fn schedule_problems() {
+    let schedule = "- [ ] 09:00--10:00 Work\n- [ ] 09:30--10:00 Call\n- [ ] 14:00 Walk\n";
+    let state = run(schedule, "");
+    assert_eq!(state.screen()[9], "                       1 overlap, 1 gap");
+
+    // Moving the call after work solves the overlap, and the gap is allowed
+    let state = run(schedule, "jtrl<Esc>:set gap 4:00<CR>");
+    assert_eq!(start(&state, 1), Some(Time::hm(10, 30)));
+    assert_eq!(state.screen()[9], "gap = 4h");
+}
+
+#[test]
+fn reorder_by_time() {
+    // In absolute mode the item moves into order, the cursor with it
+    let state = run(SCHEDULE, "trllll<Esc>");
+    assert_eq!(summaries(&state), vec!["Lunch", "Work", "Walk"]);
+    assert_eq!(start(&state, 1), Some(Time::hm(13, 0)));
+    assert_eq!(state.cursor_pos().unwrap().line, 1);
+
+    // Untimed items stay below their timed item
+    let state = run(
+        "<!-- wake-up: 07:00 -->\n- [ ] 14:00 Walk\n- [ ] Water plants\n- [ ] 09:00 Work\n",
+        "G:sort<CR>",
+    );
+    assert_eq!(summaries(&state), vec!["Work", "Walk", "Water plants"]);
+    assert_eq!(state.cursor_pos().unwrap().line, 0);
+}
+
+#[test]
+fn move_time_boxes() {
+    // The item moves with its time, the cursor with it
+    let state = run(SCHEDULE, "<A-j>");
+    assert_eq!(summaries(&state), vec!["Lunch", "Work", "Walk"]);
+    assert_eq!(start(&state, 1), Some(Time::hm(9, 0)));
+    assert_eq!(state.cursor_pos().unwrap().line, 1);
+
+    let state = run(SCHEDULE, "2<A-j><A-k>");
+    assert_eq!(summaries(&state), vec!["Lunch", "Work", "Walk"]);
+    let state = run(SCHEDULE, "G5<A-j>");
+    assert_eq!(summaries(&state), vec!["Work", "Lunch", "Walk"]);
+
+    // Activities trade time slots, the times stay in order
+    let state = run(SCHEDULE, "G<A-K>");
+    assert_eq!(summaries(&state), vec!["Work", "Walk", "Lunch"]);
+    assert_eq!(state.screen()[1], "12:00--13:00 (1h) Walk");
+    assert_eq!(start(&state, 2), Some(Time::hm(14, 0)));
+
+    // The clipboard is left alone, and moves are undone in one step
+    let state = run(SCHEDULE, "dd<A-j>p");
+    assert_eq!(summaries(&state), vec!["Walk", "Lunch", "Work"]);
+    let state = run(SCHEDULE, "2<A-j>u");
+    assert_eq!(summaries(&state), vec!["Work", "Lunch", "Walk"]);
+}
+
+#[test]
+fn yank_and_registers() {
+    // Yanking leaves the schedule alone and is not an undo step
+    let state = run(SCHEDULE, "yyp");
+    assert_eq!(summaries(&state), vec!["Work", "Work", "Lunch", "Walk"]);
+    let state = run(SCHEDULE, "2yy");
+    assert_eq!(state.screen()[9], "2 items yanked");
+    let state = run(SCHEDULE, "2yyu");
+    assert_eq!(state.screen()[9], "already at oldest change");
+    let state = run(SCHEDULE, "jykGP");
+    assert_eq!(
+        summaries(&state),
+        vec!["Work", "Lunch", "Work", "Lunch", "Walk"]
+    );
+
+    // Named registers survive other cuts, upper case names append
+    let state = run(SCHEDULE, "\"ayyjdd\"ap");
+    assert_eq!(summaries(&state), vec!["Work", "Walk", "Work"]);
+    let state = run(SCHEDULE, "\"ayyG\"Ayyddgg\"aP");
+    assert_eq!(summaries(&state), vec!["Work", "Walk", "Work", "Lunch"]);
+
+    // The clipboard is written with OSC 52
+    let state = run(SCHEDULE, "\"+yy");
+    let output = String::from_utf8_lossy(state.output());
+    assert!(output.contains("\x1b]52;c;LSBbIF0gMDk6MDAgV29yaw==\x07"));
+    assert_eq!(state.screen()[9], "copied 1 item to the clipboard");
+    let state = run(SCHEDULE, ":yank +<Enter>");
+    assert_eq!(state.screen()[9], "copied 3 items to the clipboard");
+}
+
+#[test]
+fn visual_mode() {
+    // The selection grows from where it started in either direction
+    let state = run(SCHEDULE, "jVjd");
+    assert_eq!(summaries(&state), vec!["Work"]);
+    assert_eq!(*state.mode.borrow(), Mode::Cursor);
+    let state = run(SCHEDULE, "GVkyp");
+    assert_eq!(
+        summaries(&state),
+        vec!["Work", "Lunch", "Lunch", "Walk", "Walk"]
+    );
+    assert_eq!(state.cursor_pos().unwrap().line, 2);
+
+    // Commands act on every selected item, and the selection stays
+    let state = run(SCHEDULE, "Vj<C-a>f>>");
+    assert_eq!(*state.mode.borrow(), Mode::Visual);
+    assert!(state.schedule.timeboxes[..2]
+        .iter()
+        .all(|time_box| time_box.done && time_box.adjust_policy == AdjustPolicy::Fixed));
+    assert!(!state.schedule.timeboxes[2].done);
+    assert_eq!(start(&state, 0), Some(Time::hm(9, 30)));
+    assert_eq!(start(&state, 1), Some(Time::hm(12, 30)));
+    assert_eq!(start(&state, 2), Some(Time::hm(14, 0)));
+    assert_eq!(state.screen()[9], "-- VISUAL LINE --");
+
+    let state = run(SCHEDULE, "jVG:shift -1:00<Enter>:kind meal<Enter>D<Esc>");
+    assert_eq!(start(&state, 0), Some(Time::hm(9, 0)));
+    assert!(state.schedule.timeboxes[1..]
+        .iter()
+        .all(|time_box| time_box.time.is_none() && time_box.activity.kind == ActivityKind::Meal));
+    assert_eq!(
+        state.schedule.timeboxes[0].activity.kind,
+        ActivityKind::Unknown
+    );
+
+    // Some done, so all are crossed over, and one undo step for the lot
+    let state = run("- [x] 09:00 Work\n- [ ] 14:00 Walk\n", "Vj<C-a>");
+    assert!(state
+        .schedule
+        .timeboxes
+        .iter()
+        .all(|time_box| time_box.done));
+    let state = run(SCHEDULE, "VG>u");
+    assert_eq!(start(&state, 2), Some(Time::hm(14, 0)));
+}
+
+#[test]
+fn save_and_quit() {
+    let dir = std::env::temp_dir().join(format!("daily-planner-save-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    // A directory can not be made where a file is
+    let blocker = dir.join("blocker");
+    std::fs::write(&blocker, "").unwrap();
+
+    let mut state = run(SCHEDULE, "");
+    state.file = Some(blocker.join("today.md"));
+    state.events_mut().push_script(":wq<CR>").unwrap();
+    state.run().unwrap();
+    assert!(!state.has_quit());
+    assert!(state.screen()[9].starts_with("could not save"));
+    // Keys are still handled
+    state.events_mut().push_script("j").unwrap();
+    state.run().unwrap();
+    assert_eq!(state.cursor_pos().unwrap().line, 1);
+
+    state.file = Some(dir.join("today.md"));
+    state.events_mut().push_script(":wq<CR>").unwrap();
+    state.run().unwrap();
+    assert!(state.has_quit());
+    assert!(dir.join("today.md").exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn open_file() {
+    let dir = std::env::temp_dir().join(format!("daily-planner-open-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let other = dir.join("other.md");
+    std::fs::write(&other, "- [ ] 10:00 Read\n").unwrap();
+
+    // The edits before opening can not be undone into the other file
+    let script = format!("dd:e {}<CR>u", other.display());
+    let state = run(SCHEDULE, &script);
+    assert_eq!(state.file.as_deref(), Some(other.as_path()));
+    assert_eq!(summaries(&state), vec!["Read"]);
+    assert_eq!(state.screen()[9], "already at oldest change");
+
+    // Edits of the opened file are
+    let script = format!(":e {}<CR>ddu", other.display());
+    let state = run(SCHEDULE, &script);
+    assert_eq!(summaries(&state), vec!["Read"]);
+
+    // A file without items opens with an empty line
+    let empty = dir.join("empty.md");
+    std::fs::write(&empty, "<!-- wake-up: 09:00 -->\n").unwrap();
+    let script = format!("G:e {}<CR>jdd", empty.display());
+    let state = run(SCHEDULE, &script);
+    assert_eq!(summaries(&state), vec![""]);
+    assert_eq!(state.cursor_pos().unwrap().line, 0);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn now_line_between_items() {
+    let state = run_at(SCHEDULE, "jj", |_| Time::hm(13, 30));
+    assert!(state.screen()[2].starts_with("13:30 ─"));
+    assert!(state.screen()[3].starts_with("14:00"));
+    // The cursor moves over the now line onto the next item
+    assert_eq!(state.cursor_pos().unwrap().line, 2);
+}
+
+#[test]
+fn resize_while_typing_a_command() {
+    let schedule = Schedule::from_str(SCHEDULE).unwrap();
+    let mut events = ScriptedEvents::from_script(":set span").unwrap();
+    events.push(Event::Resize(30, 6));
+    events.push_script(" 2:00<CR>").unwrap();
+    let mut state = State::new(vec![], events, (40, 10), schedule);
+    state.clock = |_| Time::hm(22, 0);
+    state.run().unwrap();
+
+    assert_eq!(state.span_len, Duration::hm(2, 0));
+    let screen = state.screen();
+    assert_eq!(screen.len(), 6);
+    assert!(screen.iter().all(|row| row.chars().count() <= 30));
+}